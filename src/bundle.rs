@@ -0,0 +1,208 @@
+// vault_rpg/src/bundle.rs
+
+use sha2::{Digest, Sha256};
+
+use crate::error::VaultError;
+use crate::format::{Cipher, Kdf, Reader};
+use crate::storage::{self, Credentials};
+use crate::vault::VaultName;
+
+/// 导出包魔数，后面紧跟一个版本字节
+const BUNDLE_MAGIC: &[u8; 8] = b"VRPGBNDL";
+const BUNDLE_VERSION: u8 = 1;
+/// 写入加密文件头并参与认证的名称，防止把保险库文件当成导出包解密
+const BUNDLE_NAME: &str = "vault_rpg export bundle";
+/// 导出包的推荐扩展名
+pub const BUNDLE_EXTENSION: &str = "vrpg";
+
+/// 导出包中的一个保险库：原样保存的保险库文件（仍由各自的密码加密）及其元数据
+#[derive(Debug, Clone)]
+pub struct BundledVault {
+    pub name: VaultName,
+    /// 导出时保险库文件的修改时间（Unix 秒数），未知时为 0
+    pub modified: u64,
+    pub data: Vec<u8>,
+}
+
+/// 导出包：`BUNDLE_MAGIC | version | 用导出密码加密的内容`，加密格式与保险库文件相同
+///
+/// 加密的内容依次为清单 `created(u64) | count(u16)`、每个保险库的元数据
+/// `name_len(u16) | name | modified(u64) | size(u32) | sha256`，最后是按同样顺序拼接的保险库文件。
+#[derive(Debug, Clone, Default)]
+pub struct Bundle {
+    /// 导出时间（Unix 秒数）
+    pub created: u64,
+    pub vaults: Vec<BundledVault>,
+}
+
+impl Bundle {
+    /// 用导出密码加密整个导出包
+    pub fn seal(&self, credentials: &Credentials, kdf: Kdf, cipher: Cipher) -> Result<Vec<u8>, VaultError> {
+        if self.vaults.len() > u16::MAX as usize {
+            return Err(VaultError::InvalidInput("Too many vaults for one bundle".into()));
+        }
+        let mut plaintext = Vec::new();
+        plaintext.extend(self.created.to_le_bytes());
+        plaintext.extend((self.vaults.len() as u16).to_le_bytes());
+        for vault in &self.vaults {
+            let size = u32::try_from(vault.data.len())
+                .map_err(|_| VaultError::InvalidInput(format!("Vault '{}' is too large to export", vault.name)))?;
+            plaintext.extend((vault.name.len() as u16).to_le_bytes());
+            plaintext.extend(vault.name.as_bytes());
+            plaintext.extend(vault.modified.to_le_bytes());
+            plaintext.extend(size.to_le_bytes());
+            plaintext.extend(Sha256::digest(&vault.data));
+        }
+        for vault in &self.vaults {
+            plaintext.extend(&vault.data);
+        }
+
+        let mut output = BUNDLE_MAGIC.to_vec();
+        output.push(BUNDLE_VERSION);
+        output.extend(storage::encrypt_bytes(&plaintext, credentials, kdf, cipher, BUNDLE_NAME)?);
+        Ok(output)
+    }
+
+    /// 解密并校验导出包：清单中的名称必须合法且不重复，每个文件的 SHA-256 必须与清单一致
+    pub fn open(encoded: &[u8], credentials: &Credentials) -> Result<Self, VaultError> {
+        let encrypted = encoded
+            .strip_prefix(BUNDLE_MAGIC.as_slice())
+            .ok_or_else(|| VaultError::Format("not a vault_rpg export bundle".into()))?;
+        match encrypted.split_first() {
+            Some((&BUNDLE_VERSION, encrypted)) => {
+                let plaintext = storage::decrypt_bytes(encrypted, credentials, BUNDLE_NAME)?;
+                Self::parse(&plaintext)
+            }
+            Some((version, _)) => Err(VaultError::Format(format!("unsupported bundle version {}", version))),
+            None => Err(VaultError::Truncated),
+        }
+    }
+
+    fn parse(plaintext: &[u8]) -> Result<Self, VaultError> {
+        let mut reader = Reader::new(plaintext);
+        let created = reader.u64()?;
+        let count = reader.u16()? as usize;
+        let mut manifest: Vec<(VaultName, u64, usize, &[u8])> = Vec::with_capacity(count);
+        for _ in 0..count {
+            let name_len = reader.u16()? as usize;
+            let name = std::str::from_utf8(reader.take(name_len)?).map_err(|_| VaultError::InvalidUtf8)?;
+            let parsed: VaultName = name.parse()?;
+            if parsed.as_str() != name {
+                return Err(VaultError::Format(format!("vault name '{}' in the bundle is not normalized", name)));
+            }
+            if manifest.iter().any(|(other, ..)| *other == parsed) {
+                return Err(VaultError::Format(format!("vault '{}' appears twice in the bundle", name)));
+            }
+            let modified = reader.u64()?;
+            let size = reader.u32()? as usize;
+            let digest = reader.take(32)?;
+            manifest.push((parsed, modified, size, digest));
+        }
+
+        let mut vaults = Vec::with_capacity(count);
+        for (name, modified, size, digest) in manifest {
+            let data = reader.take(size)?;
+            if Sha256::digest(data).as_slice() != digest {
+                return Err(VaultError::Format(format!("vault '{}' in the bundle does not match its checksum", name)));
+            }
+            vaults.push(BundledVault { name, modified, data: data.to_vec() });
+        }
+        if !reader.is_empty() {
+            return Err(VaultError::Format("trailing data after the bundled vaults".into()));
+        }
+        Ok(Bundle { created, vaults })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secret::SecretString;
+
+    const KDF: Kdf = Kdf::Pbkdf2Sha256 { rounds: 1 };
+
+    fn bundle() -> Bundle {
+        Bundle {
+            created: 1_700_000_000,
+            vaults: vec![
+                BundledVault { name: "wallet".parse().unwrap(), modified: 1_690_000_000, data: b"first vault".to_vec() },
+                BundledVault { name: "钱包".parse().unwrap(), modified: 0, data: vec![0xab; 300] },
+            ],
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let passphrase = SecretString::from("export");
+        let credentials = Credentials::new(&passphrase);
+        let sealed = bundle().seal(&credentials, KDF, Cipher::XChaCha20Poly1305).unwrap();
+        assert!(sealed.starts_with(BUNDLE_MAGIC));
+
+        let opened = Bundle::open(&sealed, &credentials).unwrap();
+        assert_eq!(opened.created, 1_700_000_000);
+        assert_eq!(opened.vaults.len(), 2);
+        for (opened, original) in opened.vaults.iter().zip(&bundle().vaults) {
+            assert_eq!(opened.name, original.name);
+            assert_eq!(opened.modified, original.modified);
+            assert_eq!(opened.data, original.data);
+        }
+
+        let empty = Bundle { created: 1, vaults: Vec::new() }.seal(&credentials, KDF, Cipher::Aes256Gcm).unwrap();
+        assert!(Bundle::open(&empty, &credentials).unwrap().vaults.is_empty());
+    }
+
+    #[test]
+    fn rejects_wrong_passphrase() {
+        let (right, wrong) = (SecretString::from("export"), SecretString::from("wrong"));
+        let sealed = bundle().seal(&Credentials::new(&right), KDF, Cipher::Aes256Gcm).unwrap();
+        assert!(matches!(Bundle::open(&sealed, &Credentials::new(&wrong)), Err(VaultError::Decryption)));
+    }
+
+    #[test]
+    fn rejects_bad_magic_version_and_truncation() {
+        let passphrase = SecretString::from("export");
+        let credentials = Credentials::new(&passphrase);
+        let sealed = bundle().seal(&credentials, KDF, Cipher::Aes256Gcm).unwrap();
+
+        // 保险库文件不是导出包
+        let vault = storage::encrypt_bytes(b"vault", &credentials, KDF, Cipher::Aes256Gcm, "wallet").unwrap();
+        assert!(matches!(Bundle::open(&vault, &credentials), Err(VaultError::Format(_))));
+        // 去掉魔数和版本后的内容是合法的加密数据，但名称不是导出包
+        assert!(Bundle::open(&sealed[BUNDLE_MAGIC.len() + 1..], &credentials).is_err());
+
+        let mut version = sealed.clone();
+        version[BUNDLE_MAGIC.len()] = BUNDLE_VERSION + 1;
+        assert!(matches!(Bundle::open(&version, &credentials), Err(VaultError::Format(_))));
+
+        assert!(matches!(Bundle::open(BUNDLE_MAGIC, &credentials), Err(VaultError::Truncated)));
+        for len in [BUNDLE_MAGIC.len() + 1, BUNDLE_MAGIC.len() + 10, sealed.len() - 1] {
+            assert!(Bundle::open(&sealed[..len], &credentials).is_err(), "{}", len);
+        }
+    }
+
+    #[test]
+    fn rejects_inconsistent_manifests() {
+        let mut plaintext = Vec::new();
+        plaintext.extend(1u64.to_le_bytes());
+        plaintext.extend(1u16.to_le_bytes());
+        plaintext.extend(6u16.to_le_bytes());
+        plaintext.extend(b"wallet");
+        plaintext.extend(0u64.to_le_bytes());
+        plaintext.extend(4u32.to_le_bytes());
+        plaintext.extend(Sha256::digest(b"data"));
+
+        let mut good = plaintext.clone();
+        good.extend(b"data");
+        assert_eq!(Bundle::parse(&good).unwrap().vaults[0].data, b"data");
+
+        let mut tampered = plaintext.clone();
+        tampered.extend(b"DATA");
+        assert!(matches!(Bundle::parse(&tampered), Err(VaultError::Format(_))));
+
+        let mut trailing = good.clone();
+        trailing.push(0);
+        assert!(matches!(Bundle::parse(&trailing), Err(VaultError::Format(_))));
+
+        assert!(matches!(Bundle::parse(&plaintext), Err(VaultError::Truncated)));
+    }
+}
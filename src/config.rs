@@ -0,0 +1,139 @@
+// vault_rpg/src/config.rs
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::error::VaultError;
+use crate::format::Kdf;
+
+/// 配置文件路径（Linux 上为 `$XDG_CONFIG_HOME/vault_rpg/vault_rpg.conf`），
+/// 格式为每行一个 `key = value`，`#` 开头为注释
+pub fn config_path() -> PathBuf {
+    dirs::config_dir()
+        .map(|dir| dir.join("vault_rpg"))
+        .unwrap_or_default()
+        .join("vault_rpg.conf")
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    values: BTreeMap<String, String>,
+}
+
+impl Config {
+    /// 读取配置文件，文件不存在时返回空配置
+    pub fn load() -> Result<Self, VaultError> {
+        let text = match std::fs::read_to_string(config_path()) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut values = BTreeMap::new();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some((key, value)) = line.split_once('=') {
+                values.insert(key.trim().to_string(), value.trim().to_string());
+            }
+        }
+        Ok(Config { values })
+    }
+
+    pub fn save(&self) -> Result<(), VaultError> {
+        let path = config_path();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut text = String::from("# Vault RPG configuration\n");
+        for (key, value) in &self.values {
+            text.push_str(&format!("{} = {}\n", key, value));
+        }
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    pub fn set(&mut self, key: &str, value: impl ToString) {
+        self.values.insert(key.to_string(), value.to_string());
+    }
+
+    fn get_parsed<T: std::str::FromStr>(&self, key: &str) -> Result<Option<T>, VaultError> {
+        match self.get(key) {
+            Some(value) => value
+                .parse()
+                .map(Some)
+                .map_err(|_| {
+                    VaultError::Config(format!(
+                        "invalid value for '{}' in {}: {}",
+                        key,
+                        config_path().display(),
+                        value
+                    ))
+                }),
+            None => Ok(None),
+        }
+    }
+
+    /// 读取某个 KDF（argon2id / scrypt / pbkdf2）已保存的参数
+    pub fn kdf(&self, name: &str) -> Result<Option<Kdf>, VaultError> {
+        let kdf = match name {
+            "argon2id" => {
+                match (
+                    self.get_parsed("argon2id.memory_kib")?,
+                    self.get_parsed("argon2id.iterations")?,
+                    self.get_parsed("argon2id.parallelism")?,
+                ) {
+                    (Some(memory_kib), Some(iterations), Some(parallelism)) => Some(Kdf::Argon2id {
+                        memory_kib,
+                        iterations,
+                        parallelism,
+                    }),
+                    _ => None,
+                }
+            }
+            "scrypt" => {
+                match (
+                    self.get_parsed("scrypt.log_n")?,
+                    self.get_parsed("scrypt.r")?,
+                    self.get_parsed("scrypt.p")?,
+                ) {
+                    (Some(log_n), Some(r), Some(p)) => Some(Kdf::Scrypt { log_n, r, p }),
+                    _ => None,
+                }
+            }
+            "pbkdf2" => self
+                .get_parsed("pbkdf2.rounds")?
+                .map(|rounds| Kdf::Pbkdf2Sha256 { rounds }),
+            _ => None,
+        };
+        Ok(kdf)
+    }
+
+    pub fn set_kdf(&mut self, kdf: &Kdf) {
+        match kdf {
+            Kdf::Argon2id { memory_kib, iterations, parallelism } => {
+                self.set("argon2id.memory_kib", memory_kib);
+                self.set("argon2id.iterations", iterations);
+                self.set("argon2id.parallelism", parallelism);
+            }
+            Kdf::Scrypt { log_n, r, p } => {
+                self.set("scrypt.log_n", log_n);
+                self.set("scrypt.r", r);
+                self.set("scrypt.p", p);
+            }
+            Kdf::Pbkdf2Sha256 { rounds } => {
+                self.set("pbkdf2.rounds", rounds);
+            }
+        }
+    }
+
+    /// 新建保险库时默认使用的 KDF 名称
+    pub fn default_kdf_name(&self) -> Option<&str> {
+        self.get("kdf")
+    }
+}
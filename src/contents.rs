@@ -0,0 +1,366 @@
+// vault_rpg/src/contents.rs
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use zeroize::Zeroizing;
+
+use crate::error::VaultError;
+use crate::format::Reader;
+use crate::secret::SecretString;
+
+/// 结构化正文的标记。早期的正文就是助记词文本，不会以 0 字节开头
+const CONTENTS_MAGIC: &[u8] = b"\0VRPG-CONTENTS";
+/// - v1：助记词和可选的 BIP-39 密码
+/// - v2：多个带标签、类型和时间戳的条目
+const CONTENTS_VERSION: u8 = 2;
+
+const V1_FIELD_MNEMONIC: u8 = 1;
+const V1_FIELD_SEED_PASSPHRASE: u8 = 2;
+
+/// 新建保险库时助记词条目的标签，也是从旧格式读取时使用的标签
+pub const DEFAULT_MNEMONIC_LABEL: &str = "mnemonic";
+pub const MAX_LABEL_LEN: usize = 64;
+
+/// 条目类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    Mnemonic,
+    PrivateKey,
+    Password,
+    Note,
+    /// TOTP 密钥（Base32）
+    Totp,
+}
+
+impl EntryKind {
+    pub const ALL: [EntryKind; 5] = [
+        EntryKind::Mnemonic,
+        EntryKind::PrivateKey,
+        EntryKind::Password,
+        EntryKind::Note,
+        EntryKind::Totp,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EntryKind::Mnemonic => "mnemonic",
+            EntryKind::PrivateKey => "private-key",
+            EntryKind::Password => "password",
+            EntryKind::Note => "note",
+            EntryKind::Totp => "totp",
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Self, VaultError> {
+        let name = name.to_lowercase().replace('_', "-");
+        EntryKind::ALL.into_iter().find(|kind| kind.name() == name).ok_or_else(|| {
+            let names: Vec<&str> = EntryKind::ALL.iter().map(EntryKind::name).collect();
+            VaultError::InvalidInput(format!("Unsupported entry type '{}', expected one of: {}", name, names.join(", ")))
+        })
+    }
+
+    fn id(&self) -> u8 {
+        match self {
+            EntryKind::Mnemonic => 1,
+            EntryKind::PrivateKey => 2,
+            EntryKind::Password => 3,
+            EntryKind::Note => 4,
+            EntryKind::Totp => 5,
+        }
+    }
+
+    fn from_id(id: u8) -> Result<Self, VaultError> {
+        EntryKind::ALL
+            .into_iter()
+            .find(|kind| kind.id() == id)
+            .ok_or_else(|| VaultError::Format(format!("unknown entry type {}", id)))
+    }
+}
+
+/// 保险库中的一个秘密条目，时间戳为 Unix 秒数（旧格式升级而来的条目为 0）
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub label: String,
+    pub kind: EntryKind,
+    pub secret: SecretString,
+    /// 助记词条目的 BIP-39 可选密码（“第 25 个词”），与保险库主密码无关，参与钱包种子的生成
+    pub seed_passphrase: Option<SecretString>,
+    pub created: u64,
+    pub modified: u64,
+}
+
+impl Entry {
+    pub fn new(label: &str, kind: EntryKind, secret: SecretString) -> Result<Self, VaultError> {
+        let now = now();
+        Ok(Entry {
+            label: check_label(label)?,
+            kind,
+            secret,
+            seed_passphrase: None,
+            created: now,
+            modified: now,
+        })
+    }
+
+    /// 设置 BIP-39 密码，空字符串等同于不使用
+    pub fn set_seed_passphrase(&mut self, seed_passphrase: Option<SecretString>) -> Result<(), VaultError> {
+        let seed_passphrase = seed_passphrase.filter(|passphrase| !passphrase.expose().is_empty());
+        if seed_passphrase.is_some() && self.kind != EntryKind::Mnemonic {
+            return Err(VaultError::InvalidInput(format!(
+                "Entry '{}' is a {}, only mnemonics take a BIP-39 passphrase",
+                self.label,
+                self.kind.name()
+            )));
+        }
+        self.seed_passphrase = seed_passphrase;
+        Ok(())
+    }
+
+    /// 更新修改时间
+    pub fn touch(&mut self) {
+        self.modified = now();
+    }
+}
+
+/// 保险库解密后的内容：按添加顺序排列的条目，标签互不相同
+///
+/// v2 布局：`CONTENTS_MAGIC | version | count(u16) | entry*`，每个条目为
+/// `kind | label_len(u16) | label | created(u64) | modified(u64) | secret_len(u32) | secret
+/// | passphrase_len(u32) | passphrase`，长度为 0 的 BIP-39 密码表示未设置。
+#[derive(Debug, Clone, Default)]
+pub struct Contents {
+    pub entries: Vec<Entry>,
+}
+
+impl Contents {
+    /// 只包含一个助记词条目的内容，空的 BIP-39 密码等同于不使用
+    pub fn new(mnemonic: SecretString, seed_passphrase: Option<SecretString>) -> Self {
+        let now = now();
+        Contents {
+            entries: vec![Entry {
+                label: DEFAULT_MNEMONIC_LABEL.to_string(),
+                kind: EntryKind::Mnemonic,
+                secret: mnemonic,
+                seed_passphrase: seed_passphrase.filter(|passphrase| !passphrase.expose().is_empty()),
+                created: now,
+                modified: now,
+            }],
+        }
+    }
+
+    pub fn get(&self, label: &str) -> Result<&Entry, VaultError> {
+        self.entries
+            .iter()
+            .find(|entry| entry.label == label)
+            .ok_or_else(|| VaultError::EntryNotFound(label.to_string()))
+    }
+
+    pub fn get_mut(&mut self, label: &str) -> Result<&mut Entry, VaultError> {
+        self.entries
+            .iter_mut()
+            .find(|entry| entry.label == label)
+            .ok_or_else(|| VaultError::EntryNotFound(label.to_string()))
+    }
+
+    pub fn add(&mut self, entry: Entry) -> Result<(), VaultError> {
+        self.check_unused(&entry.label)?;
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, label: &str) -> Result<Entry, VaultError> {
+        let index = self
+            .entries
+            .iter()
+            .position(|entry| entry.label == label)
+            .ok_or_else(|| VaultError::EntryNotFound(label.to_string()))?;
+        Ok(self.entries.remove(index))
+    }
+
+    /// 修改条目标签，新标签不能与其他条目重复
+    pub fn rename(&mut self, label: &str, new_label: &str) -> Result<(), VaultError> {
+        let new_label = check_label(new_label)?;
+        if new_label != label {
+            self.check_unused(&new_label)?;
+        }
+        let entry = self.get_mut(label)?;
+        entry.label = new_label;
+        entry.touch();
+        Ok(())
+    }
+
+    /// 钱包相关命令使用的助记词条目：指定标签时必须是助记词，否则取第一个助记词条目
+    pub fn mnemonic(&self, label: Option<&str>) -> Result<&Entry, VaultError> {
+        match label {
+            Some(label) => self.get(label).and_then(check_mnemonic),
+            None => self
+                .entries
+                .iter()
+                .find(|entry| entry.kind == EntryKind::Mnemonic)
+                .ok_or_else(|| VaultError::InvalidInput("The vault has no mnemonic entry".into())),
+        }
+    }
+
+    pub fn mnemonic_mut(&mut self, label: Option<&str>) -> Result<&mut Entry, VaultError> {
+        let label = self.mnemonic(label)?.label.clone();
+        self.get_mut(&label)
+    }
+
+    fn check_unused(&self, label: &str) -> Result<(), VaultError> {
+        if self.entries.iter().any(|entry| entry.label == label) {
+            return Err(VaultError::InvalidInput(format!("Entry '{}' already exists", label)));
+        }
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Zeroizing<Vec<u8>> {
+        let mut out = Zeroizing::new(CONTENTS_MAGIC.to_vec());
+        out.push(CONTENTS_VERSION);
+        out.extend((self.entries.len() as u16).to_le_bytes());
+        for entry in &self.entries {
+            out.push(entry.kind.id());
+            out.extend((entry.label.len() as u16).to_le_bytes());
+            out.extend_from_slice(entry.label.as_bytes());
+            out.extend(entry.created.to_le_bytes());
+            out.extend(entry.modified.to_le_bytes());
+            push_secret(&mut out, &entry.secret);
+            push_secret(&mut out, entry.seed_passphrase.as_ref().unwrap_or(&SecretString::default()));
+        }
+        out
+    }
+
+    /// 解析解密后的正文，没有标记的旧正文整体视为助记词
+    pub fn from_bytes(plaintext: &[u8]) -> Result<Self, VaultError> {
+        let Some(body) = plaintext.strip_prefix(CONTENTS_MAGIC) else {
+            let mnemonic = std::str::from_utf8(plaintext).map_err(|_| VaultError::InvalidUtf8)?;
+            return Ok(Contents::legacy(SecretString::from(mnemonic), None));
+        };
+
+        let mut reader = Reader::new(body);
+        match reader.u8()? {
+            1 => parse_v1(&mut reader),
+            2 => {
+                let count = reader.u16()? as usize;
+                let mut contents = Contents::default();
+                for _ in 0..count {
+                    let kind = EntryKind::from_id(reader.u8()?)?;
+                    let label_len = reader.u16()? as usize;
+                    let label = std::str::from_utf8(reader.take(label_len)?).map_err(|_| VaultError::InvalidUtf8)?;
+                    let created = reader.u64()?;
+                    let modified = reader.u64()?;
+                    let secret = read_secret(&mut reader)?;
+                    let seed_passphrase = read_secret(&mut reader)?;
+                    contents.add(Entry {
+                        label: label.to_string(),
+                        kind,
+                        secret,
+                        seed_passphrase: (!seed_passphrase.expose().is_empty()).then_some(seed_passphrase),
+                        created,
+                        modified,
+                    })?;
+                }
+                if !reader.is_empty() {
+                    return Err(VaultError::Format("trailing data after the last entry".into()));
+                }
+                Ok(contents)
+            }
+            version => Err(VaultError::Format(format!("unsupported contents version {}", version))),
+        }
+    }
+
+    /// 旧格式只有一个助记词，创建时间未知
+    fn legacy(mnemonic: SecretString, seed_passphrase: Option<SecretString>) -> Self {
+        Contents {
+            entries: vec![Entry {
+                label: DEFAULT_MNEMONIC_LABEL.to_string(),
+                kind: EntryKind::Mnemonic,
+                secret: mnemonic,
+                seed_passphrase,
+                created: 0,
+                modified: 0,
+            }],
+        }
+    }
+}
+
+/// v1：`(field_id | len(u32) | value)*`
+fn parse_v1(reader: &mut Reader) -> Result<Contents, VaultError> {
+    let mut mnemonic = None;
+    let mut seed_passphrase = None;
+    while !reader.is_empty() {
+        let field = reader.u8()?;
+        let value = read_secret(reader)?;
+        match field {
+            V1_FIELD_MNEMONIC => mnemonic = Some(value),
+            V1_FIELD_SEED_PASSPHRASE => seed_passphrase = Some(value),
+            _ => return Err(VaultError::Format(format!("unknown contents field {}", field))),
+        }
+    }
+    let mnemonic = mnemonic.ok_or_else(|| VaultError::Format("vault contents have no mnemonic".into()))?;
+    Ok(Contents::legacy(mnemonic, seed_passphrase))
+}
+
+fn check_mnemonic(entry: &Entry) -> Result<&Entry, VaultError> {
+    if entry.kind != EntryKind::Mnemonic {
+        return Err(VaultError::InvalidInput(format!(
+            "Entry '{}' is a {}, not a mnemonic",
+            entry.label,
+            entry.kind.name()
+        )));
+    }
+    Ok(entry)
+}
+
+/// 标签去掉首尾空白后不能为空，最长 `MAX_LABEL_LEN` 个字符
+fn check_label(label: &str) -> Result<String, VaultError> {
+    let label = label.trim();
+    if label.is_empty() || label.chars().count() > MAX_LABEL_LEN || label.chars().any(char::is_control) {
+        return Err(VaultError::InvalidInput(format!(
+            "Entry labels must be 1 to {} printable characters",
+            MAX_LABEL_LEN
+        )));
+    }
+    Ok(label.to_string())
+}
+
+pub fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// 把 Unix 秒数格式化为 `YYYY-MM-DD HH:MM UTC`，0 表示未知
+pub fn format_timestamp(secs: u64) -> String {
+    if secs == 0 {
+        return "unknown".to_string();
+    }
+    let (year, month, day, hour, minute, _) = utc_datetime(secs);
+    format!("{:04}-{:02}-{:02} {:02}:{:02} UTC", year, month, day, hour, minute)
+}
+
+/// 把 Unix 秒数拆成 UTC 的 (年, 月, 日, 时, 分, 秒)
+pub fn utc_datetime(secs: u64) -> (i64, i64, i64, u64, u64, u64) {
+    // Howard Hinnant 的 civil_from_days 算法
+    let days = (secs / 86_400) as i64;
+    let (hour, minute, second) = (secs % 86_400 / 3600, secs % 3600 / 60, secs % 60);
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day, hour, minute, second)
+}
+
+fn push_secret(out: &mut Vec<u8>, value: &SecretString) {
+    let value = value.expose().as_bytes();
+    out.extend((value.len() as u32).to_le_bytes());
+    out.extend_from_slice(value);
+}
+
+fn read_secret(reader: &mut Reader) -> Result<SecretString, VaultError> {
+    let len = reader.u32()? as usize;
+    let value = std::str::from_utf8(reader.take(len)?).map_err(|_| VaultError::InvalidUtf8)?;
+    Ok(SecretString::from(value))
+}
@@ -0,0 +1,131 @@
+// vault_rpg/src/error.rs
+
+use std::fmt;
+
+/// 保险库相关操作的统一错误类型
+#[derive(Debug)]
+pub enum VaultError {
+    /// 命令行参数或用户输入不合法
+    InvalidInput(String),
+    /// 指定的保险库不存在
+    NotFound(String),
+    /// 保险库已存在
+    AlreadyExists(String),
+    /// 文件读写失败
+    Io(std::io::Error),
+    /// v0 文件的 base64 内容无法解码
+    Base64(base64::DecodeError),
+    /// 文件长度不足，无法包含完整的文件头或密文
+    Truncated,
+    /// 文件头格式错误或使用了不支持的版本 / 算法
+    Format(String),
+    /// KDF 参数无效或派生失败
+    Kdf(String),
+    /// 加密失败
+    Encryption,
+    /// 认证失败：口令错误或文件被篡改
+    Decryption,
+    /// 文件记录的保险库名称与请求的不一致
+    IdentityMismatch { expected: String, found: String },
+    /// 解密后的内容不是合法的 UTF-8
+    InvalidUtf8,
+    /// 配置文件内容无效
+    Config(String),
+    /// TOTP 生成或校验失败
+    Totp(String),
+    /// 未能解开谜题
+    PuzzleFailed,
+    /// 保险库需要密钥文件但未提供
+    KeyfileRequired(String),
+    /// 秘密份额无效或无法合并
+    Share(String),
+    /// 助记词不符合 BIP-39
+    InvalidMnemonic(String),
+    /// 钱包密钥或地址派生失败
+    Wallet(String),
+    /// 保险库中没有指定标签的条目
+    EntryNotFound(String),
+}
+
+impl VaultError {
+    /// 进程退出码，每一类错误对应一个固定的值（2 与 clap 的参数错误保持一致）
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            VaultError::InvalidInput(_) => 2,
+            VaultError::NotFound(_) => 3,
+            VaultError::AlreadyExists(_) => 4,
+            VaultError::Io(_) => 5,
+            VaultError::Base64(_) => 6,
+            VaultError::Truncated => 7,
+            VaultError::Format(_) => 8,
+            VaultError::Kdf(_) => 9,
+            VaultError::Encryption => 10,
+            VaultError::Decryption => 11,
+            VaultError::IdentityMismatch { .. } => 12,
+            VaultError::InvalidUtf8 => 13,
+            VaultError::Config(_) => 14,
+            VaultError::Totp(_) => 15,
+            VaultError::PuzzleFailed => 16,
+            VaultError::KeyfileRequired(_) => 17,
+            VaultError::Share(_) => 18,
+            VaultError::InvalidMnemonic(_) => 19,
+            VaultError::Wallet(_) => 20,
+            VaultError::EntryNotFound(_) => 21,
+        }
+    }
+}
+
+impl fmt::Display for VaultError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VaultError::InvalidInput(msg) => write!(f, "{}", msg),
+            VaultError::NotFound(name) => write!(f, "The specified vault '{}' does not exist.", name),
+            VaultError::AlreadyExists(name) => write!(f, "Vault '{}' already exists!", name),
+            VaultError::Io(e) => write!(f, "I/O error: {}", e),
+            VaultError::Base64(e) => write!(f, "base64 decode failed: {}", e),
+            VaultError::Truncated => write!(f, "Vault file is truncated"),
+            VaultError::Format(msg) => write!(f, "Invalid vault file: {}", msg),
+            VaultError::Kdf(msg) => write!(f, "Key derivation failed: {}", msg),
+            VaultError::Encryption => write!(f, "Encryption failed"),
+            VaultError::Decryption => write!(f, "Decryption failed: wrong passphrase or tampered vault file"),
+            VaultError::IdentityMismatch { expected, found } => write!(
+                f,
+                "Vault identity mismatch: file belongs to vault '{}', not '{}'",
+                found, expected
+            ),
+            VaultError::InvalidUtf8 => write!(f, "Decrypted data is not valid UTF-8"),
+            VaultError::Config(msg) => write!(f, "Invalid config: {}", msg),
+            VaultError::Totp(msg) => write!(f, "TOTP error: {}", msg),
+            VaultError::PuzzleFailed => write!(f, "You failed to solve the puzzle, the treasure still sleeps deep in the dungeon..."),
+            VaultError::KeyfileRequired(name) => {
+                write!(f, "Vault '{}' requires a keyfile, pass it with --keyfile <path>", name)
+            }
+            VaultError::Share(msg) => write!(f, "Invalid share: {}", msg),
+            VaultError::InvalidMnemonic(msg) => write!(f, "Not a valid BIP-39 mnemonic: {}", msg),
+            VaultError::Wallet(msg) => write!(f, "Wallet derivation failed: {}", msg),
+            VaultError::EntryNotFound(label) => write!(f, "The vault has no entry labelled '{}'", label),
+        }
+    }
+}
+
+impl std::error::Error for VaultError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            VaultError::Io(e) => Some(e),
+            VaultError::Base64(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for VaultError {
+    fn from(e: std::io::Error) -> Self {
+        VaultError::Io(e)
+    }
+}
+
+impl From<base64::DecodeError> for VaultError {
+    fn from(e: base64::DecodeError) -> Self {
+        VaultError::Base64(e)
+    }
+}
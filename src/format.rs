@@ -0,0 +1,690 @@
+// vault_rpg/src/format.rs
+
+use std::fmt;
+
+use base64::{Engine as _, engine::general_purpose};
+
+use crate::error::VaultError;
+
+/// 保险库文件魔数，后面紧跟一个格式版本字节
+pub const MAGIC: &[u8; 4] = b"VRPG";
+/// 当前写入的文件格式版本
+///
+/// - v1：文件头 + 密文
+/// - v2：文件头中记录保险库名称，整个文件头作为 AEAD 关联数据参与认证
+/// - v3：追加标志位字节（如是否需要密钥文件）
+/// - v4：正文由随机数据密钥加密，数据密钥由多个独立的密钥槽分别包装
+pub const FORMAT_VERSION: u8 = 4;
+const MIN_VERSIONED: u8 = 1;
+
+/// 每个保险库最多的密钥槽数量
+pub const MAX_KEY_SLOTS: usize = 8;
+
+/// 密钥槽解锁需要密钥文件
+pub const FLAG_KEYFILE: u8 = 0x01;
+const KNOWN_FLAGS: u8 = FLAG_KEYFILE;
+
+/// KDF 参数上限，超出的文件头直接拒绝，避免打开恶意文件时耗尽内存或长时间卡住
+///
+/// scrypt 的 log_n 上限：r = 8 时 N = 2^20 占用 1 GiB 内存
+pub const SCRYPT_MAX_LOG_N: u8 = 20;
+/// scrypt 的内存上限（128 · r · N 字节）
+const SCRYPT_MAX_MEMORY: u64 = 1 << 30;
+const SCRYPT_MAX_P: u32 = 64;
+/// 与 scrypt 相同的 1 GiB 内存上限
+pub const ARGON2_MAX_MEMORY_KIB: u32 = 1024 * 1024;
+pub const ARGON2_MAX_ITERATIONS: u32 = 64;
+const ARGON2_MAX_PARALLELISM: u32 = 64;
+pub const PBKDF2_MAX_ROUNDS: u32 = 50_000_000;
+
+/// v0（无文件头）格式的固定参数
+const V0_PBKDF2_ROUNDS: u32 = 100_000;
+const V0_SALT_LEN: usize = 16;
+const V0_NONCE_LEN: usize = 12;
+/// 所有支持的 AEAD 算法的认证标签长度
+const TAG_LEN: usize = 16;
+
+const KDF_PBKDF2_SHA256: u8 = 1;
+const KDF_ARGON2ID: u8 = 2;
+const KDF_SCRYPT: u8 = 3;
+const CIPHER_AES256_GCM: u8 = 1;
+const CIPHER_XCHACHA20_POLY1305: u8 = 2;
+const CIPHER_AES256_GCM_SIV: u8 = 3;
+
+/// 密钥派生算法及其参数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kdf {
+    Pbkdf2Sha256 { rounds: u32 },
+    /// `memory_kib` 以 KiB 为单位
+    Argon2id { memory_kib: u32, iterations: u32, parallelism: u32 },
+    /// N = 2^log_n
+    Scrypt { log_n: u8, r: u32, p: u32 },
+}
+
+impl Kdf {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Kdf::Pbkdf2Sha256 { .. } => "pbkdf2",
+            Kdf::Argon2id { .. } => "argon2id",
+            Kdf::Scrypt { .. } => "scrypt",
+        }
+    }
+
+    fn id(&self) -> u8 {
+        match self {
+            Kdf::Pbkdf2Sha256 { .. } => KDF_PBKDF2_SHA256,
+            Kdf::Argon2id { .. } => KDF_ARGON2ID,
+            Kdf::Scrypt { .. } => KDF_SCRYPT,
+        }
+    }
+
+    fn encode_params(&self) -> Vec<u8> {
+        match self {
+            Kdf::Pbkdf2Sha256 { rounds } => rounds.to_le_bytes().to_vec(),
+            Kdf::Argon2id { memory_kib, iterations, parallelism } => {
+                let mut out = Vec::new();
+                out.extend(memory_kib.to_le_bytes());
+                out.extend(iterations.to_le_bytes());
+                out.extend(parallelism.to_le_bytes());
+                out
+            }
+            Kdf::Scrypt { log_n, r, p } => {
+                let mut out = vec![*log_n];
+                out.extend(r.to_le_bytes());
+                out.extend(p.to_le_bytes());
+                out
+            }
+        }
+    }
+
+    fn decode(id: u8, params: &[u8]) -> Result<Self, VaultError> {
+        let mut reader = Reader::new(params);
+        let kdf = match id {
+            KDF_PBKDF2_SHA256 => Kdf::Pbkdf2Sha256 { rounds: reader.u32()? },
+            KDF_ARGON2ID => Kdf::Argon2id {
+                memory_kib: reader.u32()?,
+                iterations: reader.u32()?,
+                parallelism: reader.u32()?,
+            },
+            KDF_SCRYPT => Kdf::Scrypt {
+                log_n: reader.u8()?,
+                r: reader.u32()?,
+                p: reader.u32()?,
+            },
+            _ => return Err(VaultError::Format(format!("unsupported KDF id {}", id))),
+        };
+        if !reader.is_empty() {
+            return Err(VaultError::Format("trailing bytes in KDF parameters".into()));
+        }
+        kdf.check_limits().map_err(VaultError::Format)?;
+        Ok(kdf)
+    }
+
+    /// 检查参数是否超出内存与耗时上限，超出时返回原因
+    pub fn check_limits(&self) -> Result<(), String> {
+        match *self {
+            Kdf::Pbkdf2Sha256 { rounds } if rounds > PBKDF2_MAX_ROUNDS => {
+                Err(format!("pbkdf2 rounds {} exceed the limit of {}", rounds, PBKDF2_MAX_ROUNDS))
+            }
+            Kdf::Argon2id { memory_kib, .. } if memory_kib > ARGON2_MAX_MEMORY_KIB => Err(format!(
+                "argon2id memory cost {} KiB exceeds the limit of {} KiB",
+                memory_kib, ARGON2_MAX_MEMORY_KIB
+            )),
+            Kdf::Argon2id { iterations, .. } if iterations > ARGON2_MAX_ITERATIONS => Err(format!(
+                "argon2id iterations {} exceed the limit of {}",
+                iterations, ARGON2_MAX_ITERATIONS
+            )),
+            Kdf::Argon2id { parallelism, .. } if parallelism > ARGON2_MAX_PARALLELISM => Err(format!(
+                "argon2id parallelism {} exceeds the limit of {}",
+                parallelism, ARGON2_MAX_PARALLELISM
+            )),
+            Kdf::Scrypt { log_n, r, .. }
+                if log_n > SCRYPT_MAX_LOG_N || 128 * r as u64 * (1u64 << log_n) > SCRYPT_MAX_MEMORY =>
+            {
+                Err(format!("scrypt parameters (log_n {}, r {}) exceed the 1 GiB memory limit", log_n, r))
+            }
+            Kdf::Scrypt { p, .. } if p > SCRYPT_MAX_P => {
+                Err(format!("scrypt parallelism {} exceeds the limit of {}", p, SCRYPT_MAX_P))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for Kdf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Kdf::Pbkdf2Sha256 { rounds } => write!(f, "pbkdf2 (rounds {})", rounds),
+            Kdf::Argon2id { memory_kib, iterations, parallelism } => write!(
+                f,
+                "argon2id (memory {} KiB, iterations {}, parallelism {})",
+                memory_kib, iterations, parallelism
+            ),
+            Kdf::Scrypt { log_n, r, p } => write!(f, "scrypt (log_n {}, r {}, p {})", log_n, r, p),
+        }
+    }
+}
+
+/// 对称加密算法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cipher {
+    Aes256Gcm,
+    /// 192 位随机 nonce，无需硬件 AES 指令也能常数时间运行
+    XChaCha20Poly1305,
+    /// nonce 重复时不会泄露明文之外的信息
+    Aes256GcmSiv,
+}
+
+impl Cipher {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Cipher::Aes256Gcm => "aes256gcm",
+            Cipher::XChaCha20Poly1305 => "xchacha20poly1305",
+            Cipher::Aes256GcmSiv => "aes256gcmsiv",
+        }
+    }
+
+    fn id(&self) -> u8 {
+        match self {
+            Cipher::Aes256Gcm => CIPHER_AES256_GCM,
+            Cipher::XChaCha20Poly1305 => CIPHER_XCHACHA20_POLY1305,
+            Cipher::Aes256GcmSiv => CIPHER_AES256_GCM_SIV,
+        }
+    }
+
+    fn from_id(id: u8) -> Result<Self, VaultError> {
+        match id {
+            CIPHER_AES256_GCM => Ok(Cipher::Aes256Gcm),
+            CIPHER_XCHACHA20_POLY1305 => Ok(Cipher::XChaCha20Poly1305),
+            CIPHER_AES256_GCM_SIV => Ok(Cipher::Aes256GcmSiv),
+            _ => Err(VaultError::Format(format!("unsupported cipher id {}", id))),
+        }
+    }
+
+    pub fn nonce_len(&self) -> usize {
+        match self {
+            Cipher::Aes256Gcm | Cipher::Aes256GcmSiv => 12,
+            Cipher::XChaCha20Poly1305 => 24,
+        }
+    }
+}
+
+/// 密钥槽：用一组凭据（口令 + 可选密钥文件）派生出的密钥包装数据密钥
+///
+/// 布局：`id | label_len | label | flags | kdf_id | kdf_params_len(u16) | kdf_params | salt_len | salt |
+/// cipher_id | nonce_len | nonce | wrapped_len | wrapped_key`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeySlot {
+    pub id: u8,
+    pub label: String,
+    pub flags: u8,
+    pub kdf: Kdf,
+    pub salt: Vec<u8>,
+    pub cipher: Cipher,
+    /// v4 之前的文件没有包装过的数据密钥，以下两项为空
+    pub nonce: Vec<u8>,
+    pub wrapped_key: Vec<u8>,
+}
+
+impl KeySlot {
+    pub fn requires_keyfile(&self) -> bool {
+        self.flags & FLAG_KEYFILE != 0
+    }
+
+    fn metadata_bytes(&self) -> Vec<u8> {
+        let params = self.kdf.encode_params();
+        let mut out = vec![self.id, self.label.len() as u8];
+        out.extend(self.label.as_bytes());
+        out.push(self.flags);
+        out.push(self.kdf.id());
+        out.extend((params.len() as u16).to_le_bytes());
+        out.extend(&params);
+        out.push(self.salt.len() as u8);
+        out.extend(&self.salt);
+        out.push(self.cipher.id());
+        out
+    }
+
+    /// 包装数据密钥时使用的关联数据，不含保险库名称，重命名时无需重新包装
+    pub fn associated_data(&self) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        out.extend(self.metadata_bytes());
+        out
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut out = self.metadata_bytes();
+        out.push(self.nonce.len() as u8);
+        out.extend(&self.nonce);
+        out.push(self.wrapped_key.len() as u8);
+        out.extend(&self.wrapped_key);
+        out
+    }
+
+    fn parse(reader: &mut Reader) -> Result<Self, VaultError> {
+        let id = reader.u8()?;
+        let label_len = reader.u8()? as usize;
+        let label = String::from_utf8(reader.take(label_len)?.to_vec())
+            .map_err(|_| VaultError::Format("key slot label is not valid UTF-8".into()))?;
+        let flags = reader.flags()?;
+        let kdf = reader.kdf()?;
+        let salt_len = reader.u8()? as usize;
+        let salt = reader.take(salt_len)?.to_vec();
+        let cipher = Cipher::from_id(reader.u8()?)?;
+        let nonce = reader.nonce(cipher)?;
+        let wrapped_len = reader.u8()? as usize;
+        let wrapped_key = reader.take(wrapped_len)?.to_vec();
+        Ok(KeySlot {
+            id,
+            label,
+            flags,
+            kdf,
+            salt,
+            cipher,
+            nonce,
+            wrapped_key,
+        })
+    }
+}
+
+/// 保险库文件头
+///
+/// v4 布局：`MAGIC | version | cipher_id | nonce_len | nonce | name_len(u16) | name | slot_count | slots...`，
+/// 文件头之后是用数据密钥加密的密文。
+///
+/// v1-v3 布局：
+/// `MAGIC | version | kdf_id | kdf_params_len(u16) | kdf_params | salt_len | salt | cipher_id | nonce_len | nonce`，
+/// v2 在其后追加 `name_len(u16) | name`，v3 再追加 `flags`。这些旧格式没有数据密钥，
+/// 解析时表示为一个 `nonce` 与 `wrapped_key` 均为空的密钥槽。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    pub version: u8,
+    pub cipher: Cipher,
+    pub nonce: Vec<u8>,
+    /// v2 起记录的保险库名称
+    pub vault_name: Option<String>,
+    pub slots: Vec<KeySlot>,
+    /// v2、v3 文件以原始文件头作为关联数据
+    legacy_aad: Vec<u8>,
+}
+
+impl Header {
+    pub fn new(cipher: Cipher, nonce: Vec<u8>, vault_name: &str, slots: Vec<KeySlot>) -> Self {
+        Header {
+            version: FORMAT_VERSION,
+            cipher,
+            nonce,
+            vault_name: Some(vault_name.to_string()),
+            slots,
+            legacy_aad: Vec::new(),
+        }
+    }
+
+    /// v4 之前的文件直接用口令派生的密钥加密正文，没有可管理的密钥槽
+    pub fn is_legacy(&self) -> bool {
+        self.version < 4
+    }
+
+    /// 所有密钥槽都需要密钥文件
+    pub fn requires_keyfile(&self) -> bool {
+        self.slots.iter().all(KeySlot::requires_keyfile)
+    }
+
+    /// 加密正文时使用的关联数据，不包含密钥槽，增删密钥槽不影响正文
+    pub fn associated_data(&self) -> Vec<u8> {
+        if self.is_legacy() {
+            return self.legacy_aad.clone();
+        }
+        let name = self.vault_name.as_deref().unwrap_or_default().as_bytes();
+        let mut out = Vec::new();
+        out.extend(MAGIC);
+        out.push(self.version);
+        out.push(self.cipher.id());
+        out.push(self.nonce.len() as u8);
+        out.extend(&self.nonce);
+        out.extend((name.len() as u16).to_le_bytes());
+        out.extend(name);
+        out
+    }
+
+    /// 序列化为当前格式，旧格式的文件头只能读取，不能写回
+    pub fn to_bytes(&self) -> Vec<u8> {
+        debug_assert!(!self.is_legacy());
+        let mut out = self.associated_data();
+        out.push(self.slots.len() as u8);
+        for slot in &self.slots {
+            out.extend(slot.to_bytes());
+        }
+        out
+    }
+}
+
+/// 判断数据是否以带版本的文件头开头
+///
+/// v0 文件是 base64 文本，版本字节永远不会落在 base64 字符集中，因此不会误判。
+pub fn is_versioned(data: &[u8]) -> bool {
+    data.len() > MAGIC.len()
+        && data.starts_with(MAGIC)
+        && (MIN_VERSIONED..=FORMAT_VERSION).contains(&data[MAGIC.len()])
+}
+
+/// 解析保险库文件，返回文件头和密文
+pub fn parse(data: &[u8]) -> Result<(Header, Vec<u8>), VaultError> {
+    if !is_versioned(data) {
+        // base64 文本也可能恰好以 "VRPG" 开头，整个文件是合法 base64 时按 v0 解析
+        let is_base64 = general_purpose::STANDARD.decode(data.trim_ascii()).is_ok();
+        if data.starts_with(MAGIC) && data.len() > MAGIC.len() && !is_base64 {
+            return Err(VaultError::Format(format!("unsupported format version {}", data[MAGIC.len()])));
+        }
+        return parse_v0(data);
+    }
+
+    let mut reader = Reader::new(&data[MAGIC.len()..]);
+    let version = reader.u8()?;
+    let header = if version >= 4 {
+        let cipher = Cipher::from_id(reader.u8()?)?;
+        let nonce = reader.nonce(cipher)?;
+        let vault_name = reader.name()?;
+        let slot_count = reader.u8()? as usize;
+        if slot_count == 0 || slot_count > MAX_KEY_SLOTS {
+            return Err(VaultError::Format(format!("invalid key slot count {}", slot_count)));
+        }
+        let mut slots = Vec::with_capacity(slot_count);
+        for _ in 0..slot_count {
+            slots.push(KeySlot::parse(&mut reader)?);
+        }
+        Header {
+            version,
+            cipher,
+            nonce,
+            vault_name: Some(vault_name),
+            slots,
+            legacy_aad: Vec::new(),
+        }
+    } else {
+        let kdf = reader.kdf()?;
+        let salt_len = reader.u8()? as usize;
+        let salt = reader.take(salt_len)?.to_vec();
+        let cipher = Cipher::from_id(reader.u8()?)?;
+        let nonce = reader.nonce(cipher)?;
+        let vault_name = if version >= 2 { Some(reader.name()?) } else { None };
+        let flags = if version >= 3 { reader.flags()? } else { 0 };
+        let header_len = data.len() - reader.rest().len();
+        Header {
+            version,
+            cipher,
+            nonce,
+            vault_name,
+            slots: vec![legacy_slot(kdf, salt, cipher, flags)],
+            legacy_aad: if version >= 2 { data[..header_len].to_vec() } else { Vec::new() },
+        }
+    };
+
+    if reader.rest().len() < TAG_LEN {
+        return Err(VaultError::Truncated);
+    }
+    Ok((header, reader.rest().to_vec()))
+}
+
+fn legacy_slot(kdf: Kdf, salt: Vec<u8>, cipher: Cipher, flags: u8) -> KeySlot {
+    KeySlot {
+        id: 0,
+        label: String::new(),
+        flags,
+        kdf,
+        salt,
+        cipher,
+        nonce: Vec::new(),
+        wrapped_key: Vec::new(),
+    }
+}
+
+/// v0：base64(salt || nonce || ciphertext)，PBKDF2 100_000 轮 + AES-256-GCM
+fn parse_v0(encoded: &[u8]) -> Result<(Header, Vec<u8>), VaultError> {
+    let data = general_purpose::STANDARD.decode(encoded.trim_ascii())?;
+    if data.len() < V0_SALT_LEN + V0_NONCE_LEN + TAG_LEN {
+        return Err(VaultError::Truncated);
+    }
+
+    let kdf = Kdf::Pbkdf2Sha256 { rounds: V0_PBKDF2_ROUNDS };
+    let header = Header {
+        version: 0,
+        cipher: Cipher::Aes256Gcm,
+        nonce: data[V0_SALT_LEN..V0_SALT_LEN + V0_NONCE_LEN].to_vec(),
+        vault_name: None,
+        slots: vec![legacy_slot(kdf, data[..V0_SALT_LEN].to_vec(), Cipher::Aes256Gcm, 0)],
+        legacy_aad: Vec::new(),
+    };
+    Ok((header, data[V0_SALT_LEN + V0_NONCE_LEN..].to_vec()))
+}
+
+/// 按小端序逐个读取字段，长度不足时返回 `Truncated`
+pub(crate) struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Reader { data }
+    }
+
+    pub(crate) fn take(&mut self, n: usize) -> Result<&'a [u8], VaultError> {
+        if self.data.len() < n {
+            return Err(VaultError::Truncated);
+        }
+        let (head, tail) = self.data.split_at(n);
+        self.data = tail;
+        Ok(head)
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8, VaultError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(crate) fn u16(&mut self) -> Result<u16, VaultError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32, VaultError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    pub(crate) fn u64(&mut self) -> Result<u64, VaultError> {
+        let bytes = self.take(8)?;
+        Ok(u64::from_le_bytes(bytes.try_into().expect("took 8 bytes")))
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn kdf(&mut self) -> Result<Kdf, VaultError> {
+        let kdf_id = self.u8()?;
+        let params_len = self.u16()? as usize;
+        Kdf::decode(kdf_id, self.take(params_len)?)
+    }
+
+    fn nonce(&mut self, cipher: Cipher) -> Result<Vec<u8>, VaultError> {
+        let nonce_len = self.u8()? as usize;
+        if nonce_len != cipher.nonce_len() {
+            return Err(VaultError::Format("invalid nonce length".into()));
+        }
+        Ok(self.take(nonce_len)?.to_vec())
+    }
+
+    fn name(&mut self) -> Result<String, VaultError> {
+        let name_len = self.u16()? as usize;
+        String::from_utf8(self.take(name_len)?.to_vec())
+            .map_err(|_| VaultError::Format("vault name in header is not valid UTF-8".into()))
+    }
+
+    fn flags(&mut self) -> Result<u8, VaultError> {
+        let flags = self.u8()?;
+        if flags & !KNOWN_FLAGS != 0 {
+            return Err(VaultError::Format(format!("unknown flags {:#04x}", flags)));
+        }
+        Ok(flags)
+    }
+
+    fn rest(&self) -> &'a [u8] {
+        self.data
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// 按 v1-v3 布局构造旧格式的文件头（PBKDF2 + AES-256-GCM），供解析和解密测试使用
+    pub(crate) fn legacy_header(version: u8, kdf: Kdf, salt: &[u8], nonce: &[u8], name: &str, flags: u8) -> Vec<u8> {
+        let params = kdf.encode_params();
+        let mut out = MAGIC.to_vec();
+        out.push(version);
+        out.push(kdf.id());
+        out.extend((params.len() as u16).to_le_bytes());
+        out.extend(&params);
+        out.push(salt.len() as u8);
+        out.extend(salt);
+        out.push(Cipher::Aes256Gcm.id());
+        out.push(nonce.len() as u8);
+        out.extend(nonce);
+        if version >= 2 {
+            out.extend((name.len() as u16).to_le_bytes());
+            out.extend(name.as_bytes());
+        }
+        if version >= 3 {
+            out.push(flags);
+        }
+        out
+    }
+
+    fn slot(id: u8, kdf: Kdf, cipher: Cipher) -> KeySlot {
+        KeySlot {
+            id,
+            label: format!("slot {}", id),
+            flags: if id == 1 { FLAG_KEYFILE } else { 0 },
+            kdf,
+            salt: vec![id; 16],
+            cipher,
+            nonce: vec![0xa0 + id; cipher.nonce_len()],
+            wrapped_key: vec![0x55; 48],
+        }
+    }
+
+    fn v4_file() -> (Header, Vec<u8>) {
+        let slots = vec![
+            slot(0, Kdf::Argon2id { memory_kib: 65536, iterations: 3, parallelism: 1 }, Cipher::XChaCha20Poly1305),
+            slot(1, Kdf::Scrypt { log_n: 17, r: 8, p: 1 }, Cipher::Aes256Gcm),
+            slot(2, Kdf::Pbkdf2Sha256 { rounds: 600_000 }, Cipher::Aes256GcmSiv),
+        ];
+        let header = Header::new(Cipher::XChaCha20Poly1305, vec![7; 24], "wallet", slots);
+        let mut data = header.to_bytes();
+        data.extend([0xcc; 40]);
+        (header, data)
+    }
+
+    #[test]
+    fn v4_header_round_trip() {
+        let (header, data) = v4_file();
+        let (parsed, ciphertext) = parse(&data).unwrap();
+        assert_eq!(parsed, header);
+        assert_eq!(ciphertext, vec![0xcc; 40]);
+        assert!(!parsed.is_legacy());
+        assert!(!parsed.requires_keyfile());
+        assert!(parsed.slots[1].requires_keyfile());
+        assert!(parsed.associated_data().ends_with(b"\x06\x00wallet"));
+    }
+
+    #[test]
+    fn parses_legacy_headers() {
+        let kdf = Kdf::Pbkdf2Sha256 { rounds: 100_000 };
+        for version in 1..=3 {
+            let header_bytes = legacy_header(version, kdf, &[1; 16], &[2; 12], "wallet", FLAG_KEYFILE);
+            let mut data = header_bytes.clone();
+            data.extend([0xcc; 20]);
+            let (header, ciphertext) = parse(&data).unwrap();
+
+            assert_eq!(header.version, version);
+            assert!(header.is_legacy());
+            assert_eq!(header.cipher, Cipher::Aes256Gcm);
+            assert_eq!(header.nonce, vec![2; 12]);
+            assert_eq!(header.vault_name.as_deref(), (version >= 2).then_some("wallet"));
+            let flags = if version >= 3 { FLAG_KEYFILE } else { 0 };
+            assert_eq!(header.slots, vec![legacy_slot(kdf, vec![1; 16], Cipher::Aes256Gcm, flags)]);
+            let aad = if version >= 2 { header_bytes } else { Vec::new() };
+            assert_eq!(header.associated_data(), aad);
+            assert_eq!(ciphertext, vec![0xcc; 20]);
+        }
+    }
+
+    #[test]
+    fn parses_v0_base64() {
+        let mut raw = vec![1u8; V0_SALT_LEN];
+        raw.extend([2u8; V0_NONCE_LEN]);
+        raw.extend([3u8; 30]);
+        let encoded = format!("{}\n", general_purpose::STANDARD.encode(&raw));
+        let (header, ciphertext) = parse(encoded.as_bytes()).unwrap();
+        assert_eq!(header.version, 0);
+        assert_eq!(header.vault_name, None);
+        assert_eq!(header.slots[0].kdf, Kdf::Pbkdf2Sha256 { rounds: V0_PBKDF2_ROUNDS });
+        assert_eq!(header.slots[0].salt, vec![1; V0_SALT_LEN]);
+        assert_eq!(header.nonce, vec![2; V0_NONCE_LEN]);
+        assert_eq!(ciphertext, vec![3; 30]);
+    }
+
+    #[test]
+    fn v0_base64_starting_with_magic_is_not_a_versioned_header() {
+        // base64 "VRPG" 解码为 0x55 0x13 0xc6
+        let mut raw = vec![0x55, 0x13, 0xc6];
+        raw.resize(V0_SALT_LEN + V0_NONCE_LEN + TAG_LEN, 9);
+        let encoded = general_purpose::STANDARD.encode(&raw);
+        assert!(encoded.starts_with("VRPG"));
+        assert_eq!(parse(encoded.as_bytes()).unwrap().0.version, 0);
+
+        assert!(matches!(parse(b"VRPG\x09 not base64"), Err(VaultError::Format(_))));
+    }
+
+    #[test]
+    fn rejects_malformed_headers() {
+        let (_, data) = v4_file();
+        let header_len = data.len() - 40;
+
+        // 密文比认证标签还短
+        assert!(matches!(parse(&data[..header_len + TAG_LEN - 1]), Err(VaultError::Truncated)));
+        assert!(matches!(parse(&data[..header_len - 10]), Err(VaultError::Truncated)));
+
+        let mut bad_cipher = data.clone();
+        bad_cipher[MAGIC.len() + 1] = 0x7f;
+        assert!(matches!(parse(&bad_cipher), Err(VaultError::Format(_))));
+
+        let mut bad_nonce_len = data.clone();
+        bad_nonce_len[MAGIC.len() + 2] = 12;
+        assert!(matches!(parse(&bad_nonce_len), Err(VaultError::Format(_))));
+
+        let header = Header::new(Cipher::Aes256Gcm, vec![0; 12], "wallet", Vec::new());
+        let mut no_slots = header.to_bytes();
+        no_slots.extend([0; TAG_LEN]);
+        assert!(matches!(parse(&no_slots), Err(VaultError::Format(_))));
+
+        let mut flags = legacy_header(3, Kdf::Pbkdf2Sha256 { rounds: 1 }, &[1; 16], &[2; 12], "wallet", 0x80);
+        flags.extend([0; TAG_LEN]);
+        assert!(matches!(parse(&flags), Err(VaultError::Format(_))));
+    }
+
+    #[test]
+    fn rejects_kdf_parameters_over_the_limits() {
+        for kdf in [
+            Kdf::Argon2id { memory_kib: 0xffff_fff0, iterations: 3, parallelism: 1 },
+            Kdf::Argon2id { memory_kib: 65536, iterations: ARGON2_MAX_ITERATIONS + 1, parallelism: 1 },
+            Kdf::Scrypt { log_n: SCRYPT_MAX_LOG_N + 1, r: 8, p: 1 },
+            Kdf::Scrypt { log_n: 17, r: 1 << 16, p: 1 },
+            Kdf::Pbkdf2Sha256 { rounds: PBKDF2_MAX_ROUNDS + 1 },
+        ] {
+            let mut data = legacy_header(3, kdf, &[1; 16], &[2; 12], "wallet", 0);
+            data.extend([0; TAG_LEN]);
+            assert!(matches!(parse(&data), Err(VaultError::Format(_))), "{}", kdf);
+        }
+    }
+}
@@ -1,13 +1,30 @@
+mod bundle;
+mod config;
+mod contents;
+mod error;
+mod format;
+mod mnemonic;
+mod secret;
+mod shamir;
+mod slip39;
 mod storage;
 mod puzzles;
 mod totp;
 mod vault;
+mod wallet;
 
-use crate::storage::{encrypt_mnemonic, decrypt_mnemonic};
+use crate::bundle::{Bundle, BundledVault};
+use crate::config::Config;
+use crate::contents::{Contents, Entry, EntryKind};
+use crate::error::VaultError;
+use crate::format::{Cipher, Kdf};
+use crate::secret::SecretString;
+use crate::storage::{Credentials, encrypt_contents, decrypt_contents};
 use crate::puzzles::run_puzzle_game;
-use crate::vault::Vault;
+use crate::vault::{Vault, VaultName};
 use crate::totp::{TotpConfig, generate_totp_secret, get_totp_code, generate_secret};
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
+use std::path::{Path, PathBuf};
 use totp_rs::Algorithm;
 
 
@@ -15,6 +32,9 @@ use totp_rs::Algorithm;
 #[command(name = "Vault RPG")]
 #[command(about = "A mnemonic vault with puzzle games", long_about = None)]
 struct Cli {
+    /// Directory holding the vault files [env: VAULT_RPG_DIR, config: vault_dir, default: user data directory]
+    #[arg(long, global = true)]
+    vault_dir: Option<PathBuf>,
     #[command(subcommand)]
     command: Commands,
 }
@@ -31,7 +51,7 @@ enum TotpCommands {
         issuer: Option<String>,
         /// Secret key (can be set via VAULT_TOTP_SECRET env var, auto-generated if not provided)
         #[arg(short, long)]
-        secret: Option<String>,
+        secret: Option<SecretString>,
         /// Algorithm type (SHA1, SHA256, SHA512)
         #[arg(long, default_value = "SHA1")]
         algorithm: String,
@@ -52,7 +72,7 @@ enum TotpCommands {
         issuer: Option<String>,
         /// Secret key (can be set via VAULT_TOTP_SECRET env var)
         #[arg(short, long)]
-        secret: Option<String>,
+        secret: Option<SecretString>,
         /// Algorithm type (SHA1, SHA256, SHA512)
         #[arg(long, default_value = "SHA1")]
         algorithm: String,
@@ -65,6 +85,264 @@ enum TotpCommands {
     },
 }
 
+#[derive(Subcommand)]
+enum SlotCommands {
+    /// Add a key slot, the vault content is not re-encrypted
+    Add {
+        name: VaultName,
+        /// A passphrase that already unlocks the vault
+        #[arg(short, long)]
+        passphrase: SecretString,
+        /// Keyfile belonging to that passphrase, if any
+        #[arg(short, long)]
+        keyfile: Option<PathBuf>,
+        /// Passphrase of the new key slot
+        #[arg(short, long)]
+        new_passphrase: SecretString,
+        /// Also require this keyfile for the new key slot
+        #[arg(long)]
+        new_keyfile: Option<PathBuf>,
+        /// Label shown by `slot list`
+        #[arg(short, long, default_value = "")]
+        label: String,
+        #[command(flatten)]
+        kdf: KdfArgs,
+    },
+    /// List key slots (no passphrase needed)
+    List {
+        name: VaultName,
+    },
+    /// Revoke a key slot by its id
+    Revoke {
+        name: VaultName,
+        id: u8,
+        /// A passphrase that unlocks the vault (any slot)
+        #[arg(short, long)]
+        passphrase: SecretString,
+        /// Keyfile belonging to that passphrase, if any
+        #[arg(short, long)]
+        keyfile: Option<PathBuf>,
+        /// Shred the backups that still contain the revoked slot, they can no longer be restored
+        #[arg(long)]
+        shred_old_backups: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum TrashCommands {
+    /// List deleted vaults
+    List,
+    /// Restore a deleted vault
+    Restore {
+        name: VaultName,
+        /// Trash id shown by `trash list`, defaults to the most recently deleted copy
+        #[arg(long, default_value = "latest")]
+        at: String,
+    },
+    /// Permanently delete trashed vaults, each vault name has to be typed to confirm
+    Purge {
+        /// Only purge this vault
+        name: Option<VaultName>,
+        /// Only purge copies deleted longer ago than this, e.g. 30d, 12h or 2w
+        #[arg(long, value_parser = parse_age)]
+        older_than: Option<u64>,
+        /// Overwrite the files with random data before deleting them, backups included (best effort)
+        #[arg(long)]
+        shred: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum BackupsCommands {
+    /// List the backups of a vault, oldest first (works for deleted vaults too)
+    List {
+        name: VaultName,
+    },
+}
+
+#[derive(Subcommand)]
+enum SeedPassphraseCommands {
+    /// Store a BIP-39 passphrase, replacing the current one
+    Set {
+        name: VaultName,
+        #[arg(short, long)]
+        passphrase: SecretString,
+        /// Keyfile, if the vault was created with one
+        #[arg(short, long)]
+        keyfile: Option<PathBuf>,
+        /// The BIP-39 passphrase ("25th word")
+        #[arg(short, long)]
+        seed_passphrase: SecretString,
+        /// Mnemonic entry to use, defaults to the first mnemonic in the vault
+        #[arg(short, long)]
+        entry: Option<String>,
+    },
+    /// Remove the BIP-39 passphrase, addresses are derived from the mnemonic alone again
+    Clear {
+        name: VaultName,
+        #[arg(short, long)]
+        passphrase: SecretString,
+        /// Keyfile, if the vault was created with one
+        #[arg(short, long)]
+        keyfile: Option<PathBuf>,
+        /// Mnemonic entry to use, defaults to the first mnemonic in the vault
+        #[arg(short, long)]
+        entry: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum EntryCommands {
+    /// Add an entry to a vault
+    Add {
+        name: VaultName,
+        /// Label of the new entry, unique within the vault
+        label: String,
+        /// Entry type (mnemonic, private-key, password, note, totp)
+        #[arg(short = 't', long = "type")]
+        kind: String,
+        /// The secret, read from stdin if omitted
+        #[arg(short, long)]
+        secret: Option<SecretString>,
+        /// BIP-39 passphrase ("25th word") of a mnemonic entry
+        #[arg(long)]
+        seed_passphrase: Option<SecretString>,
+        /// Store a mnemonic even if it is not a valid BIP-39 mnemonic
+        #[arg(long)]
+        force: bool,
+        #[arg(short, long)]
+        passphrase: SecretString,
+        /// Keyfile, if the vault was created with one
+        #[arg(short, long)]
+        keyfile: Option<PathBuf>,
+    },
+    /// Show the secret of an entry
+    Get {
+        name: VaultName,
+        label: String,
+        #[arg(short, long)]
+        passphrase: SecretString,
+        /// Keyfile, if the vault was created with one
+        #[arg(short, long)]
+        keyfile: Option<PathBuf>,
+        /// Also print the BIP-39 passphrase of a mnemonic entry
+        #[arg(long)]
+        reveal_passphrase: bool,
+    },
+    /// Replace the secret of an entry and/or rename it
+    Edit {
+        name: VaultName,
+        label: String,
+        /// New secret; read from stdin if neither --secret nor --rename is given
+        #[arg(short, long)]
+        secret: Option<SecretString>,
+        /// New label
+        #[arg(long)]
+        rename: Option<String>,
+        /// Store a mnemonic even if it is not a valid BIP-39 mnemonic
+        #[arg(long)]
+        force: bool,
+        #[arg(short, long)]
+        passphrase: SecretString,
+        /// Keyfile, if the vault was created with one
+        #[arg(short, long)]
+        keyfile: Option<PathBuf>,
+    },
+    /// Remove an entry
+    Remove {
+        name: VaultName,
+        label: String,
+        #[arg(short, long)]
+        passphrase: SecretString,
+        /// Keyfile, if the vault was created with one
+        #[arg(short, long)]
+        keyfile: Option<PathBuf>,
+    },
+    /// List the entries of a vault, secrets are not shown
+    Ls {
+        name: VaultName,
+        #[arg(short, long)]
+        passphrase: SecretString,
+        /// Keyfile, if the vault was created with one
+        #[arg(short, long)]
+        keyfile: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum Slip39Commands {
+    /// Export the mnemonic of a vault as SLIP-39 share phrases
+    Split {
+        name: VaultName,
+        #[arg(short, long)]
+        passphrase: SecretString,
+        /// Keyfile, if the vault was created with one
+        #[arg(short, long)]
+        keyfile: Option<PathBuf>,
+        /// Number of groups needed to recover the mnemonic
+        #[arg(long, default_value = "1")]
+        group_threshold: u8,
+        /// A group of shares written as THRESHOLDofCOUNT (e.g. 3of5), repeat for each group
+        #[arg(short, long = "group", required = true)]
+        groups: Vec<slip39::GroupSpec>,
+        /// SLIP-39 passphrase protecting the shares (printable ASCII)
+        #[arg(long)]
+        slip39_passphrase: Option<SecretString>,
+        /// PBKDF2 iteration exponent, each step doubles the work
+        #[arg(long, default_value_t = slip39::DEFAULT_ITERATION_EXPONENT)]
+        iteration_exponent: u8,
+        /// Mnemonic entry to use, defaults to the first mnemonic in the vault
+        #[arg(short, long)]
+        entry: Option<String>,
+    },
+    /// Recover a mnemonic from SLIP-39 share phrases into a new vault
+    Combine {
+        name: VaultName,
+        /// Master passphrase of the new vault
+        #[arg(short, long)]
+        passphrase: SecretString,
+        /// Require this keyfile, in addition to the passphrase, to unlock the new vault
+        #[arg(short, long)]
+        keyfile: Option<PathBuf>,
+        /// A share phrase, repeat for each one; read one per line from stdin if omitted
+        #[arg(short, long = "share")]
+        shares: Vec<SecretString>,
+        /// SLIP-39 passphrase the shares were created with
+        #[arg(long)]
+        slip39_passphrase: Option<SecretString>,
+        /// Wordlist language of the original mnemonic, the shares do not record it
+        #[arg(long, default_value = "en")]
+        lang: String,
+        #[command(flatten)]
+        kdf: KdfArgs,
+        /// Cipher (xchacha20poly1305, aes256gcm, aes256gcmsiv)
+        #[arg(long)]
+        cipher: Option<String>,
+    },
+}
+
+#[derive(Args, Default)]
+struct KdfArgs {
+    /// Key derivation function (argon2id, scrypt, pbkdf2), defaults to the calibrated config
+    #[arg(long)]
+    kdf: Option<String>,
+    /// KDF memory cost in KiB (argon2id, scrypt)
+    #[arg(long)]
+    kdf_memory: Option<u32>,
+    /// KDF iterations (argon2id time cost, pbkdf2 rounds)
+    #[arg(long)]
+    kdf_iterations: Option<u32>,
+    /// KDF parallelism (argon2id lanes, scrypt p)
+    #[arg(long)]
+    kdf_parallelism: Option<u32>,
+}
+
+impl KdfArgs {
+    fn is_empty(&self) -> bool {
+        self.kdf.is_none() && self.kdf_memory.is_none() && self.kdf_iterations.is_none() && self.kdf_parallelism.is_none()
+    }
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Interactive menu
@@ -72,26 +350,302 @@ enum Commands {
 
     /// Create new vault
     Create {
-        name: String,
+        name: VaultName,
+        /// BIP-39 mnemonic (any supported wordlist language)
+        #[arg(short, long)]
+        mnemonic: SecretString,
+        /// Store the text even if it is not a valid BIP-39 mnemonic
+        #[arg(long)]
+        force: bool,
+        /// Optional BIP-39 passphrase ("25th word") of the wallet, not the vault passphrase
+        #[arg(long)]
+        seed_passphrase: Option<SecretString>,
         #[arg(short, long)]
-        mnemonic: String,
+        passphrase: SecretString,
+        /// Require this keyfile, in addition to the passphrase, to unlock the vault
         #[arg(short, long)]
-        passphrase: String,
+        keyfile: Option<PathBuf>,
+        #[command(flatten)]
+        kdf: KdfArgs,
+        /// Cipher (xchacha20poly1305, aes256gcm, aes256gcmsiv)
+        #[arg(long)]
+        cipher: Option<String>,
+    },
+
+    /// Generate a new BIP-39 mnemonic straight into a vault
+    Generate {
+        name: VaultName,
+        /// Number of words (12, 15, 18, 21, 24)
+        #[arg(short, long, default_value = "24")]
+        words: usize,
+        /// Wordlist language (en, zh, zh-hant, ja, ko, es, fr, it, pt, cs)
+        #[arg(long, default_value = "en")]
+        lang: String,
+        /// Dice rolls (digits 1-6) mixed into the OS entropy
+        #[arg(long)]
+        dice: Option<SecretString>,
+        /// Print the generated mnemonic so it can be written down
+        #[arg(long)]
+        show: bool,
+        /// Optional BIP-39 passphrase ("25th word") of the wallet, not the vault passphrase
+        #[arg(long)]
+        seed_passphrase: Option<SecretString>,
+        #[arg(short, long)]
+        passphrase: SecretString,
+        /// Require this keyfile, in addition to the passphrase, to unlock the vault
+        #[arg(short, long)]
+        keyfile: Option<PathBuf>,
+        #[command(flatten)]
+        kdf: KdfArgs,
+        /// Cipher (xchacha20poly1305, aes256gcm, aes256gcmsiv)
+        #[arg(long)]
+        cipher: Option<String>,
     },
 
     /// List all vaults
     List,
 
-    /// Delete vault
+    /// Move a vault to the trash, `trash restore` brings it back
     Delete {
-        name: String,
+        name: VaultName,
+    },
+
+    /// Rename a vault, or move it to another directory with --to-dir; its backups move with it
+    Rename {
+        name: VaultName,
+        /// New name, may be left out when moving with --to-dir
+        #[arg(required_unless_present = "to_dir")]
+        new_name: Option<VaultName>,
+        /// Move the vault into this vault directory
+        #[arg(long)]
+        to_dir: Option<PathBuf>,
+        #[arg(short, long)]
+        passphrase: SecretString,
+        /// Keyfile, if the vault was created with one
+        #[arg(short, long)]
+        keyfile: Option<PathBuf>,
+    },
+
+    /// Copy a vault under a new name, or into another directory with --to-dir (backups are not copied)
+    Copy {
+        name: VaultName,
+        /// Name of the copy, may be left out when copying with --to-dir
+        #[arg(required_unless_present = "to_dir")]
+        new_name: Option<VaultName>,
+        /// Create the copy in this vault directory
+        #[arg(long)]
+        to_dir: Option<PathBuf>,
+        #[arg(short, long)]
+        passphrase: SecretString,
+        /// Keyfile, if the vault was created with one
+        #[arg(short, long)]
+        keyfile: Option<PathBuf>,
+    },
+
+    /// Export vaults into one portable bundle file encrypted with an export passphrase
+    Export {
+        #[arg(required = true)]
+        names: Vec<VaultName>,
+        /// Bundle file to write, e.g. vaults.vrpg
+        #[arg(short, long)]
+        out: PathBuf,
+        /// Export passphrase protecting the bundle, the vaults inside keep their own passphrases
+        #[arg(short, long)]
+        passphrase: SecretString,
+        /// Overwrite the bundle file if it already exists
+        #[arg(long)]
+        force: bool,
+        #[command(flatten)]
+        kdf: KdfArgs,
+    },
+
+    /// Import the vaults of a bundle created by `export`
+    Import {
+        bundle: PathBuf,
+        /// Export passphrase of the bundle
+        #[arg(short, long)]
+        passphrase: SecretString,
+        /// What to do with a vault whose name is taken: ask, rename, skip or overwrite
+        #[arg(long, default_value = "ask", value_parser = parse_conflict)]
+        on_conflict: Conflict,
+        /// Passphrase of the bundled vaults, needed to re-bind a vault to a new name when renaming
+        #[arg(long)]
+        vault_passphrase: Option<SecretString>,
+        /// Keyfile of the bundled vaults, if they use one
+        #[arg(long)]
+        vault_keyfile: Option<PathBuf>,
+    },
+
+    /// List, restore or permanently purge deleted vaults
+    Trash {
+        #[command(subcommand)]
+        trash_command: TrashCommands,
+    },
+
+    /// Inspect the automatic backups taken before a vault is overwritten
+    Backups {
+        #[command(subcommand)]
+        backups_command: BackupsCommands,
+    },
+
+    /// Restore a vault from one of its backups, the current file is backed up first
+    Restore {
+        name: VaultName,
+        /// Backup id shown by `backups list`, or `latest`
+        #[arg(long)]
+        at: String,
     },
 
     /// Unlock vault
     Unlock {
-        name: String,
+        name: VaultName,
+        #[arg(short, long)]
+        passphrase: SecretString,
+        /// Keyfile, if the vault was created with one
+        #[arg(short, long)]
+        keyfile: Option<PathBuf>,
+        /// Also print the BIP-39 passphrase stored with the mnemonic
+        #[arg(long)]
+        reveal_passphrase: bool,
+    },
+
+    /// Split the mnemonic of a vault into Shamir shares, any `threshold` of them recover it
+    Split {
+        name: VaultName,
+        #[arg(short, long)]
+        passphrase: SecretString,
+        /// Keyfile, if the vault was created with one
+        #[arg(short, long)]
+        keyfile: Option<PathBuf>,
+        /// Number of shares to create
+        #[arg(long)]
+        shares: u8,
+        /// Number of shares needed to recover the mnemonic
+        #[arg(long)]
+        threshold: u8,
+        /// Mnemonic entry to use, defaults to the first mnemonic in the vault
+        #[arg(short, long)]
+        entry: Option<String>,
+    },
+
+    /// Combine Shamir shares back into a new vault
+    Combine {
+        name: VaultName,
+        /// Master passphrase of the new vault
+        #[arg(short, long)]
+        passphrase: SecretString,
+        /// Require this keyfile, in addition to the passphrase, to unlock the new vault
+        #[arg(short, long)]
+        keyfile: Option<PathBuf>,
+        /// A share, repeat for each one; read one per line from stdin if omitted
+        #[arg(short, long = "share")]
+        shares: Vec<SecretString>,
+        #[command(flatten)]
+        kdf: KdfArgs,
+        /// Cipher (xchacha20poly1305, aes256gcm, aes256gcmsiv)
+        #[arg(long)]
+        cipher: Option<String>,
+    },
+
+    /// SLIP-39 share export and recovery
+    Slip39 {
+        #[command(subcommand)]
+        slip39_command: Slip39Commands,
+    },
+
+    /// Show the master key fingerprint and account xpubs of the wallet in a vault
+    Fingerprint {
+        name: VaultName,
+        #[arg(short, long)]
+        passphrase: SecretString,
+        /// Keyfile, if the vault was created with one
+        #[arg(short, long)]
+        keyfile: Option<PathBuf>,
+        /// Account index
+        #[arg(long, default_value = "0")]
+        account: u32,
+        /// Mnemonic entry to use, defaults to the first mnemonic in the vault
+        #[arg(short, long)]
+        entry: Option<String>,
+    },
+
+    /// Derive receive addresses of the wallet in a vault, the mnemonic is never shown
+    Addresses {
+        name: VaultName,
+        #[arg(short, long)]
+        passphrase: SecretString,
+        /// Keyfile, if the vault was created with one
+        #[arg(short, long)]
+        keyfile: Option<PathBuf>,
+        /// Chain (btc, eth, sui)
+        #[arg(long)]
+        chain: String,
+        /// Number of addresses
+        #[arg(long, default_value = "5")]
+        count: u32,
+        /// Account index
+        #[arg(long, default_value = "0")]
+        account: u32,
+        /// Mnemonic entry to use, defaults to the first mnemonic in the vault
+        #[arg(short, long)]
+        entry: Option<String>,
+    },
+
+    /// Manage the secret entries of a vault (mnemonics, private keys, passwords, notes, TOTP seeds)
+    Entry {
+        #[command(subcommand)]
+        entry_command: EntryCommands,
+    },
+
+    /// Set or clear the BIP-39 passphrase stored with the mnemonic
+    SeedPassphrase {
+        #[command(subcommand)]
+        seed_passphrase_command: SeedPassphraseCommands,
+    },
+
+    /// Change the master passphrase of a vault
+    Rekey {
+        name: VaultName,
+        /// Current master passphrase
+        #[arg(short, long)]
+        passphrase: SecretString,
+        /// New master passphrase
+        #[arg(short, long)]
+        new_passphrase: SecretString,
+        /// Current keyfile, if the vault was created with one
         #[arg(short, long)]
-        passphrase: String,
+        keyfile: Option<PathBuf>,
+        /// Require a different keyfile from now on
+        #[arg(long, conflicts_with = "no_keyfile")]
+        new_keyfile: Option<PathBuf>,
+        /// Stop requiring a keyfile
+        #[arg(long)]
+        no_keyfile: bool,
+        /// Switch KDF settings, keeps the current ones if omitted
+        #[command(flatten)]
+        kdf: KdfArgs,
+        /// Switch cipher (xchacha20poly1305, aes256gcm, aes256gcmsiv), keeps the current one if omitted
+        #[arg(long)]
+        cipher: Option<String>,
+        /// Shred the backups that still unlock with the old passphrase, the rekey can no longer be undone
+        #[arg(long)]
+        shred_old_backups: bool,
+    },
+
+    /// Manage key slots: several passphrases / keyfiles can unlock the same vault
+    Slot {
+        #[command(subcommand)]
+        slot_command: SlotCommands,
+    },
+
+    /// Benchmark KDFs on this machine and save recommended parameters
+    KdfCalibrate {
+        /// Target unlock time in milliseconds
+        #[arg(long, default_value = "750")]
+        target_ms: u64,
+        /// KDF to use by default for new vaults (argon2id, scrypt, pbkdf2)
+        #[arg(long, default_value = "argon2id")]
+        kdf: String,
     },
 
     /// TOTP related commands
@@ -101,19 +655,714 @@ enum Commands {
     },
 }
 
-fn parse_algorithm(alg_str: &str) -> Result<Algorithm, String> {
+fn parse_algorithm(alg_str: &str) -> Result<Algorithm, VaultError> {
     match alg_str.to_uppercase().as_str() {
         "SHA1" => Ok(Algorithm::SHA1),
         "SHA256" => Ok(Algorithm::SHA256),
         "SHA512" => Ok(Algorithm::SHA512),
-        _ => Err(format!("Unsupported algorithm type: {}. Supported algorithms: SHA1, SHA256, SHA512", alg_str)),
+        _ => Err(VaultError::InvalidInput(format!(
+            "Unsupported algorithm type: {}. Supported algorithms: SHA1, SHA256, SHA512",
+            alg_str
+        ))),
+    }
+}
+
+/// 未指定的参数依次取 `base`（同类 KDF 的已校准参数）和内置默认值
+fn parse_kdf(
+    kdf_str: &str,
+    memory_kib: Option<u32>,
+    iterations: Option<u32>,
+    parallelism: Option<u32>,
+    base: Option<Kdf>,
+) -> Result<Kdf, VaultError> {
+    let kdf = match kdf_str.to_lowercase().as_str() {
+        "argon2id" => {
+            let (base_memory, base_iterations, base_parallelism) = match base {
+                Some(Kdf::Argon2id { memory_kib, iterations, parallelism }) => (memory_kib, iterations, parallelism),
+                _ => (storage::ARGON2_MEMORY_KIB, storage::ARGON2_ITERATIONS, storage::ARGON2_PARALLELISM),
+            };
+            Kdf::Argon2id {
+                memory_kib: memory_kib.unwrap_or(base_memory),
+                iterations: iterations.unwrap_or(base_iterations),
+                parallelism: parallelism.unwrap_or(base_parallelism),
+            }
+        }
+        "scrypt" => {
+            if iterations.is_some() {
+                return Err(VaultError::InvalidInput(
+                    "scrypt does not take an iteration count, use --kdf-memory instead".into(),
+                ));
+            }
+            let (base_log_n, base_r, base_p) = match base {
+                Some(Kdf::Scrypt { log_n, r, p }) => (log_n, r, p),
+                _ => (storage::SCRYPT_LOG_N, storage::SCRYPT_R, storage::SCRYPT_P),
+            };
+            // r = 8 时 scrypt 占用 N KiB 内存，取不超过给定内存的最大 2 的幂
+            let (log_n, r) = match memory_kib {
+                Some(0) => return Err(VaultError::InvalidInput("KDF memory cost must be greater than zero".into())),
+                Some(kib) => (kib.ilog2() as u8, storage::SCRYPT_R),
+                None => (base_log_n, base_r),
+            };
+            Kdf::Scrypt {
+                log_n,
+                r,
+                p: parallelism.unwrap_or(base_p),
+            }
+        }
+        "pbkdf2" => {
+            if memory_kib.is_some() || parallelism.is_some() {
+                return Err(VaultError::InvalidInput("pbkdf2 only supports --kdf-iterations".into()));
+            }
+            let base_rounds = match base {
+                Some(Kdf::Pbkdf2Sha256 { rounds }) => rounds,
+                _ => storage::PBKDF2_ROUNDS,
+            };
+            Kdf::Pbkdf2Sha256 {
+                rounds: iterations.unwrap_or(base_rounds),
+            }
+        }
+        _ => {
+            return Err(VaultError::InvalidInput(format!(
+                "Unsupported KDF: {}. Supported KDFs: argon2id, scrypt, pbkdf2",
+                kdf_str
+            )));
+        }
+    };
+    kdf.check_limits().map_err(VaultError::InvalidInput)?;
+    storage::check_strength(&kdf)?;
+    Ok(kdf)
+}
+
+/// 结合命令行参数与 `kdf-calibrate` 保存的配置确定新保险库使用的 KDF
+fn resolve_kdf(args: &KdfArgs) -> Result<Kdf, VaultError> {
+    let config = Config::load()?;
+    let name = args
+        .kdf
+        .as_deref()
+        .or(config.default_kdf_name())
+        .unwrap_or(storage::DEFAULT_KDF.name())
+        .to_lowercase();
+    let base = config.kdf(&name)?;
+    parse_kdf(&name, args.kdf_memory, args.kdf_iterations, args.kdf_parallelism, base)
+}
+
+fn parse_cipher(cipher_str: &str) -> Result<Cipher, VaultError> {
+    match cipher_str.to_lowercase().replace(['-', '_'], "").as_str() {
+        "aes256gcm" => Ok(Cipher::Aes256Gcm),
+        "xchacha20poly1305" => Ok(Cipher::XChaCha20Poly1305),
+        "aes256gcmsiv" => Ok(Cipher::Aes256GcmSiv),
+        _ => Err(VaultError::InvalidInput(format!(
+            "Unsupported cipher: {}. Supported ciphers: xchacha20poly1305, aes256gcm, aes256gcmsiv",
+            cipher_str
+        ))),
+    }
+}
+
+/// 导入时遇到同名保险库的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Conflict {
+    Ask,
+    Rename,
+    Skip,
+    Overwrite,
+}
+
+fn parse_conflict(conflict: &str) -> Result<Conflict, VaultError> {
+    match conflict.to_lowercase().as_str() {
+        "ask" => Ok(Conflict::Ask),
+        "rename" => Ok(Conflict::Rename),
+        "skip" => Ok(Conflict::Skip),
+        "overwrite" => Ok(Conflict::Overwrite),
+        _ => Err(VaultError::InvalidInput(format!(
+            "Unsupported conflict policy '{}', expected ask, rename, skip or overwrite",
+            conflict
+        ))),
+    }
+}
+
+/// 解析 `30d`、`12h`、`2w` 之类的时长，返回秒数（s/m/h/d/w）
+fn parse_age(age: &str) -> Result<u64, VaultError> {
+    let invalid = || VaultError::InvalidInput(format!("Invalid duration '{}', expected e.g. 30d, 12h or 2w", age));
+    let age = age.trim();
+    let unit = match age.chars().last().ok_or_else(invalid)? {
+        's' => 1,
+        'm' => 60,
+        'h' => 3600,
+        'd' => 86_400,
+        'w' => 7 * 86_400,
+        _ => return Err(invalid()),
+    };
+    let count: u64 = age[..age.len() - 1].parse().map_err(|_| invalid())?;
+    count.checked_mul(unit).ok_or_else(invalid)
+}
+
+fn credentials<'a>(passphrase: &'a SecretString, keyfile: Option<&Path>) -> Result<Credentials<'a>, VaultError> {
+    let credentials = Credentials::new(passphrase);
+    match keyfile {
+        Some(path) => credentials.with_keyfile(path),
+        None => Ok(credentials),
+    }
+}
+
+/// 用命令行指定的 KDF / 加密算法新建保险库，已存在时报错
+fn create_new_vault(
+    dir: &Path,
+    name: &VaultName,
+    contents: &Contents,
+    credentials: &Credentials,
+    kdf_args: &KdfArgs,
+    cipher_str: Option<&str>,
+) -> Result<Vault, VaultError> {
+    let kdf = resolve_kdf(kdf_args)?;
+    let cipher = match cipher_str {
+        Some(cipher_str) => parse_cipher(cipher_str)?,
+        None => storage::DEFAULT_CIPHER,
+    };
+    let vault = Vault::new(dir, name);
+    let _lock = vault.lock_new()?;
+    let ciphertext = encrypt_contents(contents, credentials, kdf, cipher, &vault.name)?;
+    vault.save(&ciphertext)?;
+    Ok(vault)
+}
+
+/// 从标准输入逐行读取份额，空行或输入结束时停止
+fn read_shares() -> Vec<SecretString> {
+    println!("Enter one share per line, finish with an empty line:");
+    let mut shares = Vec::new();
+    loop {
+        let line = read_secret_line();
+        if line.expose().is_empty() {
+            return shares;
+        }
+        shares.push(line);
+    }
+}
+
+/// 解锁保险库，由其中的助记词条目和 BIP-39 密码生成钱包种子，同时返回是否使用了 BIP-39 密码
+fn unlock_seed(
+    dir: &Path,
+    name: &VaultName,
+    credentials: &Credentials,
+    entry: Option<&str>,
+) -> Result<(zeroize::Zeroizing<[u8; 64]>, bool), VaultError> {
+    let vault = Vault::new(dir, name);
+    let ciphertext = vault.load()?;
+    let contents = decrypt_contents(&ciphertext, credentials, &vault.name)?;
+    let entry = contents.mnemonic(entry)?;
+    let seed = wallet::seed(&entry.secret, &entry.seed_passphrase.clone().unwrap_or_default())?;
+    Ok((seed, entry.seed_passphrase.is_some()))
+}
+
+/// 解锁保险库，修改内容后重新加密保存，返回 `update` 的结果
+fn update_vault<T>(
+    dir: &Path,
+    name: &VaultName,
+    credentials: &Credentials,
+    update: impl FnOnce(&mut Contents) -> Result<T, VaultError>,
+) -> Result<T, VaultError> {
+    let vault = Vault::new(dir, name);
+    let _lock = vault.lock()?;
+    let ciphertext = vault.load()?;
+    let (new_ciphertext, result) = storage::update_contents(&ciphertext, &vault.name, credentials, update)?;
+    vault.save(&new_ciphertext)?;
+    Ok(result)
+}
+
+fn master_fingerprint(seed: &[u8]) -> Result<String, VaultError> {
+    let master = wallet::ExtendedKey::master(seed)?;
+    Ok(master.fingerprint().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// 修改保险库中的 BIP-39 密码，返回新的主密钥指纹以便与硬件钱包核对
+fn update_seed_passphrase(
+    dir: &Path,
+    name: &VaultName,
+    credentials: &Credentials,
+    entry: Option<&str>,
+    seed_passphrase: Option<&SecretString>,
+) -> Result<String, VaultError> {
+    update_vault(dir, name, credentials, |contents| {
+        let entry = contents.mnemonic_mut(entry)?;
+        let seed = wallet::seed(&entry.secret, seed_passphrase.unwrap_or(&SecretString::default()))?;
+        entry.set_seed_passphrase(seed_passphrase.cloned())?;
+        entry.touch();
+        master_fingerprint(seed.as_slice())
+    })
+}
+
+/// 校验助记词并转换为规范形式；`force` 时不合法的文本原样保留
+fn validate_mnemonic(mnemonic: &SecretString, force: bool) -> Result<SecretString, VaultError> {
+    match mnemonic::parse(mnemonic) {
+        Ok(parsed) => {
+            println!(
+                "✔️  Valid {}-word BIP-39 mnemonic ({}).",
+                parsed.word_count(),
+                mnemonic::language_name(parsed.language())
+            );
+            Ok(SecretString::new(parsed.to_string()))
+        }
+        Err(e) if force => {
+            println!("⚠️  {}, storing it as is because of --force.", e);
+            Ok(mnemonic.clone())
+        }
+        Err(e) => {
+            eprintln!("Pass --force to store text that is not a BIP-39 mnemonic.");
+            Err(e)
+        }
     }
 }
 
+/// 按条目类型检查秘密内容，助记词会转换为规范形式
+fn validate_entry_secret(kind: EntryKind, secret: SecretString, force: bool) -> Result<SecretString, VaultError> {
+    if secret.expose().is_empty() {
+        return Err(VaultError::InvalidInput("The secret of an entry cannot be empty".into()));
+    }
+    match kind {
+        EntryKind::Mnemonic => validate_mnemonic(&secret, force),
+        EntryKind::Totp => {
+            let secret = SecretString::new(secret.expose().replace(' ', "").to_uppercase());
+            totp::check_secret(&secret)?;
+            Ok(secret)
+        }
+        EntryKind::PrivateKey | EntryKind::Password | EntryKind::Note => Ok(secret),
+    }
+}
+
+/// 命令行未给出时从标准输入读取秘密
+fn secret_or_stdin(secret: &Option<SecretString>, prompt: &str) -> SecretString {
+    match secret {
+        Some(secret) => secret.clone(),
+        None => {
+            use std::io::{self, Write};
+            print!("{}: ", prompt);
+            io::stdout().flush().unwrap();
+            read_secret_line()
+        }
+    }
+}
+
+fn list_entries(dir: &Path, name: &VaultName, credentials: &Credentials) -> Result<(), VaultError> {
+    let vault = Vault::new(dir, name);
+    let contents = decrypt_contents(&vault.load()?, credentials, &vault.name)?;
+    if contents.entries.is_empty() {
+        println!("Vault '{}' has no entries.", vault.name);
+        return Ok(());
+    }
+    println!("Entries of vault '{}':", vault.name);
+    for entry in &contents.entries {
+        println!(
+            "- {} ({}{}), created {}, modified {}",
+            entry.label,
+            entry.kind.name(),
+            if entry.seed_passphrase.is_some() { ", BIP-39 passphrase" } else { "" },
+            contents::format_timestamp(entry.created),
+            contents::format_timestamp(entry.modified)
+        );
+    }
+    Ok(())
+}
+
+/// 显示条目内容；助记词的 BIP-39 密码只在 `reveal_passphrase` 时显示，TOTP 条目附带当前验证码
+fn print_entry(entry: &Entry, reveal_passphrase: bool) -> Result<(), VaultError> {
+    println!("\n🎉 {} '{}':\n{}", entry.kind.name(), entry.label, entry.secret.expose());
+    match &entry.seed_passphrase {
+        Some(seed_passphrase) if reveal_passphrase => {
+            println!("\n🔑 BIP-39 passphrase:\n{}", seed_passphrase.expose());
+        }
+        Some(_) => println!("\n🔑 This wallet also uses a BIP-39 passphrase, pass --reveal-passphrase to show it."),
+        None => {}
+    }
+    if entry.kind == EntryKind::Totp {
+        let config = TotpConfig { secret: entry.secret.clone(), ..TotpConfig::default() };
+        println!("\n🔐 Current verification code: {}", get_totp_code(&config)?);
+    }
+    Ok(())
+}
+
+/// 重命名或移动保险库，文件头中的名称随之更新
+///
+/// 能用 `credentials` 解开的备份重新绑定到新名称后一起转移，解不开的（用旧密码加密的）留在原名称下。
+/// 返回 (转移的备份数, 留下的备份数)。
+fn rename_vault(source: &Vault, target: &Vault, credentials: &Credentials) -> Result<(usize, usize), VaultError> {
+    if target.is_same(source) {
+        return Err(VaultError::InvalidInput("The new name and directory are the same as the current ones".into()));
+    }
+    let _lock = source.lock()?;
+    let _target_lock = target.lock_new()?;
+    ensure_no_leftover_backups(target)?;
+    let ciphertext = storage::rebind(&source.load()?, &source.name, &target.name, credentials)?;
+
+    let mut moved = Vec::new();
+    let mut skipped = 0;
+    for backup in source.backups()? {
+        match storage::rebind(&std::fs::read(&backup.path)?, &source.name, &target.name, credentials) {
+            Ok(rebound) => {
+                target.write_backup(&backup.id, &rebound)?;
+                moved.push(backup);
+            }
+            Err(VaultError::Decryption | VaultError::KeyfileRequired(_) | VaultError::InvalidInput(_)) => skipped += 1,
+            Err(e) => return Err(e),
+        }
+    }
+    target.save(&ciphertext)?;
+    source.remove()?;
+    source.remove_backups(&moved, false)?;
+    target.prune_backups()?;
+    Ok((moved.len(), skipped))
+}
+
+/// 新名称下还留有之前同名保险库（已删除或在回收站中）的备份时拒绝写入，
+/// 否则两者的备份会混在一起，`restore --at latest` 可能恢复出另一个保险库。调用方应持有锁
+fn ensure_no_leftover_backups(target: &Vault) -> Result<(), VaultError> {
+    if target.backups()?.is_empty() {
+        return Ok(());
+    }
+    Err(VaultError::InvalidInput(format!(
+        "'{}' still has backups of an earlier vault with that name (`backups list {}`), \
+         restore or purge that vault first, or choose another name",
+        target.name, target.name
+    )))
+}
+
+/// 导入导出包中的保险库，名称已被占用时按 `on_conflict` 处理
+///
+/// 先校验所有保险库文件再开始写入；覆盖前原文件会被备份，改名需要保险库的密码以重新绑定名称。
+fn import_bundle(
+    dir: &Path,
+    path: &Path,
+    passphrase: &SecretString,
+    on_conflict: Conflict,
+    vault_credentials: Option<&Credentials>,
+) -> Result<(), VaultError> {
+    use std::io::{self, Write};
+
+    let bundle = Bundle::open(&std::fs::read(path)?, &Credentials::new(passphrase))?;
+    for bundled in &bundle.vaults {
+        let header = storage::read_header(&bundled.data)?;
+        if let Some(recorded) = &header.vault_name
+            && recorded != bundled.name.as_str()
+        {
+            return Err(VaultError::IdentityMismatch { expected: bundled.name.to_string(), found: recorded.clone() });
+        }
+        // 自动改名所需的条件也在写入任何保险库之前检查
+        if on_conflict == Conflict::Rename && Vault::new(dir, &bundled.name).exists() {
+            if vault_credentials.is_none() {
+                return Err(VaultError::InvalidInput(
+                    "Renaming a vault on import needs --vault-passphrase".into(),
+                ));
+            }
+            free_vault_name(dir, &bundled.name)?;
+        }
+    }
+    println!(
+        "Bundle exported {} with {} vaults.",
+        contents::format_timestamp(bundle.created),
+        bundle.vaults.len()
+    );
+
+    let (mut imported, mut skipped) = (0, 0);
+    for bundled in &bundle.vaults {
+        let vault = Vault::new(dir, &bundled.name);
+        let _lock = vault.acquire_lock()?;
+        if !vault.exists() {
+            vault.save(&bundled.data)?;
+            println!("✅ Imported '{}'.", vault.name);
+            imported += 1;
+            continue;
+        }
+
+        let action = match on_conflict {
+            Conflict::Ask => loop {
+                print!(
+                    "Vault '{}' already exists (bundled copy modified {}). [r]ename, [s]kip or [o]verwrite? ",
+                    vault.name,
+                    contents::format_timestamp(bundled.modified)
+                );
+                io::stdout().flush()?;
+                let mut answer = String::new();
+                if io::stdin().read_line(&mut answer)? == 0 {
+                    break Conflict::Skip;
+                }
+                match answer.trim().to_lowercase().as_str() {
+                    "r" | "rename" => break Conflict::Rename,
+                    "s" | "skip" => break Conflict::Skip,
+                    "o" | "overwrite" => break Conflict::Overwrite,
+                    _ => continue,
+                }
+            },
+            action => action,
+        };
+        match action {
+            Conflict::Skip => {
+                println!("Skipped '{}'.", vault.name);
+                skipped += 1;
+            }
+            Conflict::Overwrite => {
+                let backed_up = vault::backup_retention()? > 0;
+                vault.save(&bundled.data)?;
+                if backed_up {
+                    println!("✅ Overwrote '{}', the previous version is in `backups list {}`.", vault.name, vault.name);
+                } else {
+                    println!("✅ Overwrote '{}' (backups are disabled, the previous version is gone).", vault.name);
+                }
+                imported += 1;
+            }
+            Conflict::Rename | Conflict::Ask => {
+                let new_name = if on_conflict == Conflict::Ask {
+                    loop {
+                        print!("New name (empty to skip): ");
+                        io::stdout().flush()?;
+                        let mut answer = String::new();
+                        if io::stdin().read_line(&mut answer)? == 0 || answer.trim().is_empty() {
+                            break None;
+                        }
+                        match answer.parse::<VaultName>() {
+                            Ok(name) if Vault::new(dir, &name).exists() => {
+                                println!("Vault '{}' already exists, pick another name.", name);
+                            }
+                            Ok(name) => break Some(name),
+                            Err(e) => println!("❌ {}", e),
+                        }
+                    }
+                } else {
+                    Some(free_vault_name(dir, &bundled.name)?)
+                };
+                let Some(new_name) = new_name else {
+                    println!("Skipped '{}'.", vault.name);
+                    skipped += 1;
+                    continue;
+                };
+                let prompted;
+                let vault_credentials = match vault_credentials {
+                    Some(vault_credentials) => vault_credentials,
+                    // `--on-conflict rename` 已在开头检查过必须提供 `--vault-passphrase`
+                    None => {
+                        print!("Passphrase of vault '{}' (to re-bind it to the new name): ", vault.name);
+                        io::stdout().flush()?;
+                        prompted = read_secret_line();
+                        &Credentials::new(&prompted)
+                    }
+                };
+                let rebound = match storage::rebind(&bundled.data, &bundled.name, &new_name, vault_credentials) {
+                    Ok(rebound) => rebound,
+                    Err(e @ (VaultError::Decryption | VaultError::KeyfileRequired(_) | VaultError::InvalidInput(_))) => {
+                        println!("⚠️  Skipped '{}': {}", vault.name, e);
+                        skipped += 1;
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                };
+                let target = Vault::new(dir, &new_name);
+                let _target_lock = target.lock_new()?;
+                target.save(&rebound)?;
+                println!("✅ Imported '{}' as '{}'.", vault.name, target.name);
+                imported += 1;
+            }
+        }
+    }
+    println!("{} imported, {} skipped.", imported, skipped);
+    Ok(())
+}
+
+/// 为改名导入的保险库找一个未被占用的名称：`名称-2`、`名称-3`……
+fn free_vault_name(dir: &Path, name: &VaultName) -> Result<VaultName, VaultError> {
+    for n in 2.. {
+        let candidate: VaultName = format!("{}-{}", name, n).parse()?;
+        if !Vault::new(dir, &candidate).exists() {
+            return Ok(candidate);
+        }
+    }
+    unreachable!("the loop only ends by returning")
+}
+
+/// 永久删除回收站中符合条件的保险库，每个保险库都要输入名称确认
+///
+/// `shred` 时先用随机数据覆盖文件，并列出所有被覆盖的文件。
+fn purge_trash(dir: &Path, name: Option<&VaultName>, older_than: Option<u64>, shred: bool) -> Result<(), VaultError> {
+    use std::io::{self, Write};
+
+    let now = contents::now();
+    let mut groups: Vec<(VaultName, Vec<vault::Snapshot>)> = Vec::new();
+    for (trashed_name, snapshot) in Vault::list_trash(dir)? {
+        if name.is_some_and(|name| *name != trashed_name)
+            || older_than.is_some_and(|age| now.saturating_sub(snapshot.created) < age)
+        {
+            continue;
+        }
+        match groups.last_mut() {
+            Some((last, snapshots)) if *last == trashed_name => snapshots.push(snapshot),
+            _ => groups.push((trashed_name, vec![snapshot])),
+        }
+    }
+    if groups.is_empty() {
+        println!("Nothing in the trash matches.");
+        return Ok(());
+    }
+
+    let mut shredded = false;
+    for (name, snapshots) in groups {
+        let vault = Vault::new(dir, &name);
+        let _lock = vault.acquire_lock()?;
+        println!(
+            "Vault '{}': {} deleted {} (oldest deleted {}).",
+            name,
+            snapshots.len(),
+            if snapshots.len() == 1 { "copy" } else { "copies" },
+            contents::format_timestamp(snapshots[0].created)
+        );
+        print!("This cannot be undone. Type the vault name to purge it: ");
+        io::stdout().flush()?;
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        if answer.trim().parse::<VaultName>().ok().as_ref() != Some(&name) {
+            println!("Skipped '{}'.", name);
+            continue;
+        }
+        let backups = vault.purge(&snapshots, shred)?;
+        if backups.is_empty() {
+            println!("🗑️  Purged '{}'.", name);
+        } else {
+            println!("🗑️  Purged '{}' and its {} backups.", name, backups.len());
+        }
+        if shred {
+            println!("Shredded files:");
+            for snapshot in snapshots.iter().chain(&backups) {
+                println!("- {} ({} bytes)", snapshot.path.display(), snapshot.size);
+            }
+            shredded = true;
+        }
+    }
+    if shredded {
+        println!(
+            "⚠️  Shredding is best effort: SSDs, copy-on-write filesystems and filesystem snapshots may still hold old copies."
+        );
+    }
+    Ok(())
+}
+
+/// 在当前 KDF 的基础上应用命令行参数，未指定时沿用当前设置
+fn rekey_kdf(kdf_args: &KdfArgs, current: Kdf) -> Result<Kdf, VaultError> {
+    if kdf_args.is_empty() {
+        return Ok(current);
+    }
+    let name = kdf_args.kdf.as_deref().unwrap_or(current.name()).to_lowercase();
+    let base = if name == current.name() {
+        Some(current)
+    } else {
+        Config::load()?.kdf(&name)?
+    };
+    parse_kdf(&name, kdf_args.kdf_memory, kdf_args.kdf_iterations, kdf_args.kdf_parallelism, base)
+}
+
+fn rekey_vault(
+    dir: &Path,
+    name: &VaultName,
+    old: &Credentials,
+    new: &Credentials,
+    kdf_args: &KdfArgs,
+    cipher_str: Option<&str>,
+    shred_old_backups: bool,
+) -> Result<(), VaultError> {
+    let vault = Vault::new(dir, name);
+    let _lock = vault.lock()?;
+    let ciphertext = vault.load()?;
+    let cipher = cipher_str.map(parse_cipher).transpose()?;
+
+    let (new_ciphertext, kdf, cipher) =
+        storage::rekey(&ciphertext, &vault.name, old, new, |current| rekey_kdf(kdf_args, current), cipher)?;
+    vault.save(&new_ciphertext)?;
+    println!(
+        "✅ Vault '{}' has been re-encrypted with a new passphrase ({}, {}).",
+        vault.name,
+        kdf,
+        cipher.name()
+    );
+    prune_stale_backups(&vault, &new_ciphertext, shred_old_backups)
+}
+
+/// 更换或撤销凭据后处理旧备份：含有当前文件中已不存在的密钥槽的备份仍能用旧凭据解锁，
+/// 默认保留（以便撤销误操作）并逐个列出警告，`shred` 时覆盖后删除。调用方应持有锁
+fn prune_stale_backups(vault: &Vault, ciphertext: &[u8], shred: bool) -> Result<(), VaultError> {
+    let current = storage::read_header(ciphertext)?;
+    let mut stale = Vec::new();
+    for backup in vault.backups()? {
+        let header = storage::read_header(&std::fs::read(&backup.path)?)?;
+        let has_old_slot = header.slots.iter().any(|slot| {
+            !current
+                .slots
+                .iter()
+                .any(|kept| kept.salt == slot.salt && kept.wrapped_key == slot.wrapped_key)
+        });
+        if has_old_slot {
+            stale.push(backup);
+        }
+    }
+    if stale.is_empty() {
+        return Ok(());
+    }
+    if shred {
+        vault.remove_backups(&stale, true)?;
+        println!("🗑️  Shredded {} backups that still unlocked with the old credentials.", stale.len());
+    } else {
+        println!("⚠️  These backups still unlock with the old credentials, and `restore` would bring them back:");
+        for backup in &stale {
+            println!("- {} ({})", backup.id, backup.path.display());
+        }
+        println!("They rotate out with `backup_retention`, --shred-old-backups destroys them as part of the change.");
+    }
+    Ok(())
+}
+
+fn list_key_slots(dir: &Path, name: &VaultName) -> Result<(), VaultError> {
+    let vault = Vault::new(dir, name);
+    let header = storage::read_header(&vault.load()?)?;
+    println!("Key slots of vault '{}' (payload cipher {}):", vault.name, header.cipher.name());
+    for slot in &header.slots {
+        println!(
+            "- [{}] {}: {}, {}{}",
+            slot.id,
+            if slot.label.is_empty() { "-" } else { &slot.label },
+            slot.kdf,
+            slot.cipher.name(),
+            if slot.requires_keyfile() { ", keyfile required" } else { "" }
+        );
+    }
+    if header.is_legacy() {
+        println!("This vault uses a legacy format, adding a key slot upgrades it.");
+    }
+    Ok(())
+}
+
+fn calibrate_kdfs(target_ms: u64, default_kdf: &str) -> Result<(), VaultError> {
+    const KDF_NAMES: [&str; 3] = ["argon2id", "scrypt", "pbkdf2"];
+    let default_kdf = default_kdf.to_lowercase();
+    if !KDF_NAMES.contains(&default_kdf.as_str()) {
+        return Err(VaultError::InvalidInput(format!(
+            "Unsupported KDF: {}. Supported KDFs: argon2id, scrypt, pbkdf2",
+            default_kdf
+        )));
+    }
+    let target = std::time::Duration::from_millis(target_ms);
+    let mut config = Config::load()?;
+
+    println!("⏱️  Calibrating KDFs for a target unlock time of {} ms...", target_ms);
+    for name in KDF_NAMES {
+        let template = parse_kdf(name, None, None, None, None)?;
+        let (kdf, elapsed) = storage::calibrate_kdf(&template, target)?;
+        println!("- {}: {} ms", kdf, elapsed.as_millis());
+        config.set_kdf(&kdf);
+    }
+
+    config.set("kdf", &default_kdf);
+    config.save()?;
+    println!("✅ Saved recommended parameters to {} (default KDF: {}).", config::config_path().display(), default_kdf);
+    Ok(())
+}
+
 fn show_menu() {
     println!("🎮 Vault RPG - 助记词保险库与解谜游戏");
     println!("=====================================");
-    println!("");
+    println!();
     println!("请选择操作：");
     println!("1. 创建新保险库");
     println!("2. 解锁保险库");
@@ -121,10 +1370,10 @@ fn show_menu() {
     println!("4. 删除保险库");
     println!("5. 生成TOTP QR码");
     println!("6. 退出");
-    println!("");
+    println!();
 }
 
-fn interactive_menu() {
+fn interactive_menu(dir: &Path) {
     loop {
         show_menu();
         print!("请输入选择 (1-6): ");
@@ -137,55 +1386,43 @@ fn interactive_menu() {
         
         match choice {
             "1" => {
-                println!("");
-                print!("请输入保险库名称: ");
-                io::stdout().flush().unwrap();
-                let mut name = String::new();
-                io::stdin().read_line(&mut name).unwrap();
-                let name = name.trim().to_string();
-                
-                print!("请输入助记词: ");
-                io::stdout().flush().unwrap();
-                let mut mnemonic = String::new();
-                io::stdin().read_line(&mut mnemonic).unwrap();
-                let mnemonic = mnemonic.trim().to_string();
-                
-                print!("请设置主密码: ");
-                io::stdout().flush().unwrap();
-                let mut passphrase = String::new();
-                io::stdin().read_line(&mut passphrase).unwrap();
-                let passphrase = passphrase.trim().to_string();
-                
-                create_vault(&name, &mnemonic, &passphrase);
+                println!();
+                if let Some(name) = read_vault_name("请输入保险库名称: ") {
+                    print!("请输入助记词: ");
+                    io::stdout().flush().unwrap();
+                    if let Some(mnemonic) = confirm_mnemonic(read_secret_line()) {
+                        print!("请输入 BIP-39 密码（没有请直接回车）: ");
+                        io::stdout().flush().unwrap();
+                        let contents = Contents::new(mnemonic, Some(read_secret_line()));
+
+                        print!("请设置主密码: ");
+                        io::stdout().flush().unwrap();
+                        let passphrase = read_secret_line();
+
+                        create_vault(dir, &name, &contents, &passphrase);
+                    } else {
+                        println!("已取消。");
+                    }
+                }
             }
             "2" => {
-                println!("");
-                print!("请输入保险库名称: ");
-                io::stdout().flush().unwrap();
-                let mut name = String::new();
-                io::stdin().read_line(&mut name).unwrap();
-                let name = name.trim().to_string();
-                
-                print!("请输入主密码: ");
-                io::stdout().flush().unwrap();
-                let mut passphrase = String::new();
-                io::stdin().read_line(&mut passphrase).unwrap();
-                let passphrase = passphrase.trim().to_string();
-                
-                unlock_vault(&name, &passphrase);
+                println!();
+                if let Some(name) = read_vault_name("请输入保险库名称: ") {
+                    print!("请输入主密码: ");
+                    io::stdout().flush().unwrap();
+                    let passphrase = read_secret_line();
+
+                    unlock_vault(dir, &name, &passphrase);
+                }
             }
             "3" => {
-                list_vaults();
+                list_vaults(dir);
             }
             "4" => {
-                println!("");
-                print!("请输入要删除的保险库名称: ");
-                io::stdout().flush().unwrap();
-                let mut name = String::new();
-                io::stdin().read_line(&mut name).unwrap();
-                let name = name.trim().to_string();
-                
-                delete_vault(&name);
+                println!();
+                if let Some(name) = read_vault_name("请输入要删除的保险库名称: ") {
+                    delete_vault(dir, &name);
+                }
             }
             "5" => {
                 generate_totp_qr();
@@ -198,24 +1435,84 @@ fn interactive_menu() {
                 println!("❌ 无效选择，请重新输入");
             }
         }
-        println!("");
+        println!();
         print!("按 Enter 继续...");
         io::stdout().flush().unwrap();
         let mut _input = String::new();
         io::stdin().read_line(&mut _input).unwrap();
-        println!("");
+        println!();
     }
 }
 
-fn create_vault(name: &str, mnemonic: &str, passphrase: &str) {
-    let vault = Vault::new(name);
-    if vault.exists() {
-        println!("保险库 '{}' 已存在！", vault.name);
-        return;
+/// 读取并校验保险库名称，不合法时显示原因并返回 `None`
+fn read_vault_name(prompt: &str) -> Option<VaultName> {
+    use std::io::{self, Write};
+
+    print!("{}", prompt);
+    io::stdout().flush().unwrap();
+    let mut name = String::new();
+    io::stdin().read_line(&mut name).unwrap();
+    match name.parse() {
+        Ok(name) => Some(name),
+        Err(e) => {
+            println!("❌ {}", e);
+            None
+        }
+    }
+}
+
+/// 读取一行敏感输入，读入缓冲区在返回前清零
+fn read_secret_line() -> SecretString {
+    use std::io;
+    use zeroize::Zeroize;
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).unwrap();
+    let secret = SecretString::from(input.trim());
+    input.zeroize();
+    secret
+}
+
+/// 校验菜单中输入的助记词，不符合 BIP-39 时询问是否仍要保存
+fn confirm_mnemonic(mnemonic: SecretString) -> Option<SecretString> {
+    use std::io::{self, Write};
+
+    match mnemonic::parse(&mnemonic) {
+        Ok(parsed) => Some(SecretString::new(parsed.to_string())),
+        Err(e) => {
+            println!("⚠️  {}", e);
+            print!("仍要保存这段文本吗？(y/N): ");
+            io::stdout().flush().unwrap();
+            let mut answer = String::new();
+            io::stdin().read_line(&mut answer).unwrap();
+            answer.trim().eq_ignore_ascii_case("y").then_some(mnemonic)
+        }
     }
-    match encrypt_mnemonic(mnemonic, passphrase) {
+}
+
+fn create_vault(dir: &Path, name: &VaultName, contents: &Contents, passphrase: &SecretString) {
+    let vault = Vault::new(dir, name);
+    let _lock = match vault.lock_new() {
+        Ok(lock) => lock,
+        Err(VaultError::AlreadyExists(_)) => {
+            println!("保险库 '{}' 已存在！", vault.name);
+            return;
+        }
+        Err(e) => {
+            eprintln!("锁定保险库失败: {}", e);
+            return;
+        }
+    };
+    let kdf = match resolve_kdf(&KdfArgs::default()) {
+        Ok(kdf) => kdf,
+        Err(e) => {
+            eprintln!("读取 KDF 配置失败: {}", e);
+            return;
+        }
+    };
+    match encrypt_contents(contents, &Credentials::new(passphrase), kdf, storage::DEFAULT_CIPHER, &vault.name) {
         Ok(ciphertext) => {
-            if let Err(e) = vault.save(ciphertext.as_bytes()) {
+            if let Err(e) = vault.save(&ciphertext) {
                 eprintln!("保存保险库文件失败: {}", e);
                 return;
             }
@@ -225,33 +1522,70 @@ fn create_vault(name: &str, mnemonic: &str, passphrase: &str) {
     }
 }
 
-fn unlock_vault(name: &str, passphrase: &str) {
-    let vault = Vault::new(name);
+fn unlock_vault(dir: &Path, name: &VaultName, passphrase: &SecretString) {
+    let vault = Vault::new(dir, name);
     if !vault.exists() {
         println!("指定的保险库 '{}' 不存在。", vault.name);
         return;
     }
     println!("💀 你回到了这个地牢...");
     if run_puzzle_game() {
-        match vault.load() {
-            Ok(ciphertext) => match decrypt_mnemonic(&ciphertext, passphrase) {
-                Ok(mnemonic) => println!("\n🎉 解锁成功！你的助记词是:\n{}", mnemonic),
-                Err(e) => eprintln!("\n❌ 解密失败: {}", e),
-            },
-            Err(e) => eprintln!("读取保险库文件失败: {}", e),
+        let ciphertext = match vault.load() {
+            Ok(ciphertext) => ciphertext,
+            Err(e) => {
+                eprintln!("读取保险库文件失败: {}", e);
+                return;
+            }
+        };
+        let header = storage::read_header(&ciphertext).ok();
+        let requires_keyfile = header.as_ref().is_some_and(|header| header.requires_keyfile());
+        let accepts_keyfile = header.as_ref().is_some_and(|header| header.slots.iter().any(|slot| slot.requires_keyfile()));
+        let keyfile = if accepts_keyfile {
+            use std::io::{self, Write};
+            if requires_keyfile {
+                print!("该保险库需要密钥文件，请输入路径: ");
+            } else {
+                print!("如使用密钥文件解锁请输入路径（直接回车跳过）: ");
+            }
+            io::stdout().flush().unwrap();
+            let mut path = String::new();
+            io::stdin().read_line(&mut path).unwrap();
+            let path = path.trim();
+            (!path.is_empty()).then(|| PathBuf::from(path))
+        } else {
+            None
+        };
+        let result = credentials(passphrase, keyfile.as_deref())
+            .and_then(|credentials| decrypt_contents(&ciphertext, &credentials, &vault.name))
+            .and_then(|contents| contents.mnemonic(None).cloned());
+        match result {
+            Ok(entry) => {
+                println!("\n🎉 解锁成功！你的助记词是:\n{}", entry.secret.expose());
+                if let Some(seed_passphrase) = &entry.seed_passphrase {
+                    use std::io::{self, Write};
+                    print!("\n该钱包还设置了 BIP-39 密码，是否显示？(y/N): ");
+                    io::stdout().flush().unwrap();
+                    let mut answer = String::new();
+                    io::stdin().read_line(&mut answer).unwrap();
+                    if answer.trim().eq_ignore_ascii_case("y") {
+                        println!("🔑 BIP-39 密码: {}", seed_passphrase.expose());
+                    }
+                }
+            }
+            Err(e) => eprintln!("\n❌ 解密失败: {}", e),
         }
     } else {
         println!("\n❌ 你未能解开谜题，宝藏仍沉睡在地牢深处...");
     }
 }
 
-fn list_vaults() {
-    match Vault::list_vaults() {
+fn list_vaults(dir: &Path) {
+    match Vault::list_vaults(dir) {
         Ok(vaults) => {
             if vaults.is_empty() {
-                println!("未找到保险库。");
+                println!("{} 中未找到保险库。", dir.display());
             } else {
-                println!("所有保险库:");
+                println!("{} 中的所有保险库:", dir.display());
                 for v in vaults {
                     println!("- {}", v);
                 }
@@ -261,22 +1595,19 @@ fn list_vaults() {
     }
 }
 
-fn delete_vault(name: &str) {
-    let vault = Vault::new(name);
-    if vault.exists() {
-        match vault.delete() {
-            Ok(_) => println!("保险库 '{}' 已删除。", vault.name),
-            Err(e) => println!("删除失败: {}", e),
-        }
-    } else {
-        println!("指定的保险库 '{}' 不存在。", vault.name);
+fn delete_vault(dir: &Path, name: &VaultName) {
+    let vault = Vault::new(dir, name);
+    match vault.lock().and_then(|_lock| vault.trash()) {
+        Ok(_) => println!("保险库 '{}' 已移到回收站，可以用 `trash restore {}` 恢复。", vault.name, vault.name),
+        Err(VaultError::NotFound(_)) => println!("指定的保险库 '{}' 不存在。", vault.name),
+        Err(e) => println!("删除失败: {}", e),
     }
 }
 
 fn generate_totp_qr() {
-    let secret_key = std::env::var("VAULT_TOTP_SECRET").unwrap_or_else(|_| {
+    let secret_key = std::env::var("VAULT_TOTP_SECRET").map(SecretString::new).unwrap_or_else(|_| {
         println!("🔑 生成密钥: JBSWY3DPEHPK3PXP");
-        "JBSWY3DPEHPK3PXP".to_string()
+        SecretString::from("JBSWY3DPEHPK3PXP")
     });
     
     let account_name = std::env::var("VAULT_TOTP_ACCOUNT").unwrap_or_else(|_| {
@@ -301,103 +1632,504 @@ fn generate_totp_qr() {
     }
 }
 
-fn main() {
-    let cli = Cli::parse();
-
-    match &cli.command {
+fn run(command: &Commands, dir: &Path) -> Result<(), VaultError> {
+    match command {
         Commands::Menu => {
-            interactive_menu();
+            interactive_menu(dir);
         }
-        Commands::Create { name, mnemonic, passphrase } => {
-            let vault = Vault::new(name);
-            if vault.exists() {
-                println!("Vault '{}' already exists!", vault.name);
-                return;
+        Commands::Create { name, mnemonic, force, seed_passphrase, passphrase, keyfile, kdf, cipher } => {
+            let credentials = credentials(passphrase, keyfile.as_deref())?;
+            let mnemonic = validate_mnemonic(mnemonic, *force)?;
+            let contents = Contents::new(mnemonic, seed_passphrase.clone());
+            let vault = create_new_vault(dir, name, &contents, &credentials, kdf, cipher.as_deref())?;
+            println!("✅ Mnemonic has been encrypted and saved in vault '{}'.", vault.name);
+            if contents.entries[0].seed_passphrase.is_some() {
+                println!("🔒 The BIP-39 passphrase has been stored with it.");
             }
-            match encrypt_mnemonic(mnemonic, passphrase) {
-                Ok(ciphertext) => {
-                    if let Err(e) = vault.save(ciphertext.as_bytes()) {
-                        eprintln!("Failed to save vault file: {}", e);
-                        return;
-                    }
-                    println!("✅ Mnemonic has been encrypted and saved in vault '{}'.", vault.name);
+        }
+
+        Commands::Generate { name, words, lang, dice, show, seed_passphrase, passphrase, keyfile, kdf, cipher } => {
+            let credentials = credentials(passphrase, keyfile.as_deref())?;
+            let language = mnemonic::parse_language(lang)?;
+            let generated = mnemonic::generate(*words, language, dice.as_ref().map(SecretString::expose))?;
+            let contents = Contents::new(SecretString::new(generated.to_string()), seed_passphrase.clone());
+            let vault = create_new_vault(dir, name, &contents, &credentials, kdf, cipher.as_deref())?;
+            println!(
+                "✅ A new {}-word {} mnemonic has been generated and saved in vault '{}'.",
+                words,
+                mnemonic::language_name(language),
+                vault.name
+            );
+            if *show {
+                println!("\n📝 Write it down and keep it offline:\n{}", contents.entries[0].secret.expose());
+            }
+        }
+
+        Commands::List => {
+            let vaults = Vault::list_vaults(dir)?;
+            if vaults.is_empty() {
+                println!("No vaults found in {}.", dir.display());
+                let legacy_dir = Path::new(vault::LEGACY_DIR);
+                if dir != legacy_dir && !Vault::list_vaults(legacy_dir)?.is_empty() {
+                    println!(
+                        "Vaults of older versions were found in ./{}, pass --vault-dir {} or move them to {}.",
+                        vault::LEGACY_DIR,
+                        vault::LEGACY_DIR,
+                        dir.display()
+                    );
+                }
+            } else {
+                println!("All vaults in {}:", dir.display());
+                for v in vaults {
+                    println!("- {}", v);
+                }
+            }
+        }
+
+        Commands::Delete { name } => {
+            let vault = Vault::new(dir, name);
+            let _lock = vault.lock()?;
+            vault.trash()?;
+            println!("Vault '{}' has been moved to the trash, `trash restore {}` brings it back.", vault.name, vault.name);
+        }
+
+        Commands::Rename { name, new_name, to_dir, passphrase, keyfile } => {
+            let credentials = credentials(passphrase, keyfile.as_deref())?;
+            let target = Vault::new(to_dir.as_deref().unwrap_or(dir), new_name.as_ref().unwrap_or(name));
+            let (moved, skipped) = rename_vault(&Vault::new(dir, name), &target, &credentials)?;
+            if target.name == *name {
+                println!("✅ Vault '{}' has been moved to {}.", name, target.path.display());
+            } else {
+                println!("✅ Vault '{}' has been renamed to '{}' ({}).", name, target.name, target.path.display());
+            }
+            if moved > 0 {
+                println!("{} backups were re-bound to the new name.", moved);
+            }
+            if skipped > 0 {
+                println!(
+                    "⚠️  {} older backups do not open with these credentials and were left under '{}', `backups list {}` shows them.",
+                    skipped, name, name
+                );
+            }
+        }
+
+        Commands::Copy { name, new_name, to_dir, passphrase, keyfile } => {
+            let credentials = credentials(passphrase, keyfile.as_deref())?;
+            let source = Vault::new(dir, name);
+            let target = Vault::new(to_dir.as_deref().unwrap_or(dir), new_name.as_ref().unwrap_or(name));
+            if target.is_same(&source) {
+                return Err(VaultError::InvalidInput("The copy needs a different name or directory".into()));
+            }
+            let _lock = source.lock()?;
+            let _target_lock = target.lock_new()?;
+            ensure_no_leftover_backups(&target)?;
+            let ciphertext = storage::rebind(&source.load()?, &source.name, &target.name, &credentials)?;
+            target.save(&ciphertext)?;
+            println!("✅ Vault '{}' has been copied to '{}' ({}).", name, target.name, target.path.display());
+            println!("The backups of '{}' were not copied, the copy starts without any.", name);
+        }
+
+        Commands::Export { names, out, passphrase, force, kdf } => {
+            if out.exists() && !force {
+                return Err(VaultError::InvalidInput(format!(
+                    "'{}' already exists, pass --force to overwrite it",
+                    out.display()
+                )));
+            }
+            let mut bundle = Bundle { created: contents::now(), vaults: Vec::new() };
+            for name in names {
+                if bundle.vaults.iter().any(|vault| vault.name == *name) {
+                    continue;
                 }
-                Err(e) => eprintln!("Encryption failed: {}", e),
+                let vault = Vault::new(dir, name);
+                let data = vault.load()?;
+                let modified = std::fs::metadata(&vault.path)?
+                    .modified()
+                    .ok()
+                    .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+                    .map_or(0, |elapsed| elapsed.as_secs());
+                bundle.vaults.push(BundledVault { name: vault.name, modified, data });
+            }
+            let encoded = bundle.seal(&Credentials::new(passphrase), resolve_kdf(kdf)?, storage::DEFAULT_CIPHER)?;
+            vault::write_atomic(out, &encoded)?;
+            println!("✅ Exported {} vaults to {}.", bundle.vaults.len(), out.display());
+            if out.extension().is_none_or(|extension| extension != bundle::BUNDLE_EXTENSION) {
+                println!("The usual extension for bundles is .{}.", bundle::BUNDLE_EXTENSION);
             }
+            println!("Importing it needs the export passphrase, unlocking the vaults still needs their own passphrases.");
+        }
+
+        Commands::Import { bundle, passphrase, on_conflict, vault_passphrase, vault_keyfile } => {
+            let vault_credentials = vault_passphrase
+                .as_ref()
+                .map(|vault_passphrase| credentials(vault_passphrase, vault_keyfile.as_deref()))
+                .transpose()?;
+            import_bundle(dir, bundle, passphrase, *on_conflict, vault_credentials.as_ref())?;
         }
 
-        Commands::List => match Vault::list_vaults() {
-            Ok(vaults) => {
-                if vaults.is_empty() {
-                    println!("No vaults found.");
+        Commands::Trash { trash_command } => match trash_command {
+            TrashCommands::List => {
+                let trashed = Vault::list_trash(dir)?;
+                if trashed.is_empty() {
+                    println!("The trash is empty.");
                 } else {
-                    println!("All vaults:");
-                    for v in vaults {
-                        println!("- {}", v);
+                    println!("Deleted vaults in {}:", dir.display());
+                    for (name, snapshot) in trashed {
+                        println!(
+                            "- {} [{}] deleted {}, {} bytes",
+                            name,
+                            snapshot.id,
+                            contents::format_timestamp(snapshot.created),
+                            snapshot.size
+                        );
                     }
                 }
             }
-            Err(e) => eprintln!("Failed to read vault list: {}", e),
+            TrashCommands::Restore { name, at } => {
+                let vault = Vault::new(dir, name);
+                let _lock = vault.lock_new()?;
+                let snapshot = vault.restore_from_trash(at)?;
+                println!(
+                    "✅ Vault '{}' has been restored (deleted {}).",
+                    vault.name,
+                    contents::format_timestamp(snapshot.created)
+                );
+            }
+            TrashCommands::Purge { name, older_than, shred } => {
+                purge_trash(dir, name.as_ref(), *older_than, *shred)?;
+            }
         },
 
-        Commands::Delete { name } => {
-            let vault = Vault::new(name);
-            if vault.exists() {
-                match vault.delete() {
-                    Ok(_) => println!("Vault '{}' has been deleted.", vault.name),
-                    Err(e) => println!("Deletion failed: {}", e),
+        Commands::Backups { backups_command } => match backups_command {
+            BackupsCommands::List { name } => {
+                let backups = Vault::new(dir, name).backups()?;
+                if backups.is_empty() {
+                    println!("Vault '{}' has no backups.", name);
+                } else {
+                    println!("Backups of vault '{}':", name);
+                    for backup in backups {
+                        println!(
+                            "- {} ({}, {} bytes)",
+                            backup.id,
+                            contents::format_timestamp(backup.created),
+                            backup.size
+                        );
+                    }
                 }
-            } else {
-                println!("The specified vault '{}' does not exist.", vault.name);
+            }
+        },
+
+        Commands::Restore { name, at } => {
+            let vault = Vault::new(dir, name);
+            let _lock = vault.acquire_lock()?;
+            let backup = vault.find_backup(at)?;
+            let ciphertext = std::fs::read(&backup.path)?;
+            storage::read_header(&ciphertext)?;
+            let replaced = vault.exists() && vault::backup_retention()? > 0;
+            vault.save(&ciphertext)?;
+            println!("✅ Vault '{}' has been restored from backup {}.", vault.name, backup.id);
+            if replaced {
+                println!("The version it replaced has been backed up, `restore {} --at latest` undoes this.", vault.name);
             }
         }
 
-        Commands::Unlock { name, passphrase } => {
-            let vault = Vault::new(name);
+        Commands::Unlock { name, passphrase, keyfile, reveal_passphrase } => {
+            let credentials = credentials(passphrase, keyfile.as_deref())?;
+            let vault = Vault::new(dir, name);
             if !vault.exists() {
-                println!("The specified vault '{}' does not exist.", vault.name);
-                return;
+                return Err(VaultError::NotFound(vault.name.to_string()));
             }
             println!("💀 You have returned to this dungeon...");
-            if run_puzzle_game() {
-                match vault.load() {
-                    Ok(ciphertext) => match decrypt_mnemonic(&ciphertext, passphrase) {
-                        Ok(mnemonic) => println!("\n🎉 Unlock successful! Your mnemonic is:\n{}", mnemonic),
-                        Err(e) => eprintln!("\n❌ Decryption failed: {}", e),
-                    },
-                    Err(e) => eprintln!("Failed to read vault file: {}", e),
+            if !run_puzzle_game() {
+                return Err(VaultError::PuzzleFailed);
+            }
+            let ciphertext = vault.load()?;
+            let contents = decrypt_contents(&ciphertext, &credentials, &vault.name)?;
+            let entry = contents.mnemonic(None)?;
+            println!("\n🎉 Unlock successful! Your mnemonic is:\n{}", entry.secret.expose());
+            match &entry.seed_passphrase {
+                Some(seed_passphrase) if *reveal_passphrase => {
+                    println!("\n🔑 BIP-39 passphrase:\n{}", seed_passphrase.expose());
                 }
-            } else {
-                println!("\n❌ You failed to solve the puzzle, the treasure still sleeps deep in the dungeon...");
+                Some(_) => println!("\n🔑 This wallet also uses a BIP-39 passphrase, pass --reveal-passphrase to show it."),
+                None => {}
+            }
+            if contents.entries.len() > 1 {
+                println!("\nℹ️  The vault holds {} more entries, see `entry ls`.", contents.entries.len() - 1);
             }
         }
 
+        Commands::Split { name, passphrase, keyfile, shares, threshold, entry } => {
+            shamir::check_parameters(*threshold, *shares)?;
+            let credentials = credentials(passphrase, keyfile.as_deref())?;
+            let vault = Vault::new(dir, name);
+            if !vault.exists() {
+                return Err(VaultError::NotFound(vault.name.to_string()));
+            }
+            println!("💀 You have returned to this dungeon...");
+            if !run_puzzle_game() {
+                return Err(VaultError::PuzzleFailed);
+            }
+            let ciphertext = vault.load()?;
+            let contents = decrypt_contents(&ciphertext, &credentials, &vault.name)?;
+            let entry = contents.mnemonic(entry.as_deref())?;
+            let shares = shamir::split(entry.secret.expose().as_bytes(), *threshold, *shares)?;
+            println!(
+                "\n🗝️  The mnemonic of vault '{}' has been split, any {} of these {} shares recover it:",
+                vault.name,
+                threshold,
+                shares.len()
+            );
+            for share in &shares {
+                println!("\nShare {}/{}:\n{}", share.index, shares.len(), share);
+            }
+            if entry.seed_passphrase.is_some() {
+                println!("\n⚠️  The BIP-39 passphrase is not part of the shares, back it up separately.");
+            }
+        }
+
+        Commands::Combine { name, passphrase, keyfile, shares, kdf, cipher } => {
+            let credentials = credentials(passphrase, keyfile.as_deref())?;
+            let shares = if shares.is_empty() { read_shares() } else { shares.clone() };
+            let shares = shares
+                .iter()
+                .map(|share| share.expose().parse())
+                .collect::<Result<Vec<shamir::Share>, VaultError>>()?;
+            let secret = shamir::combine(&shares)?;
+            let mnemonic = SecretString::from(std::str::from_utf8(&secret).map_err(|_| VaultError::InvalidUtf8)?);
+            let vault = create_new_vault(dir, name, &Contents::new(mnemonic, None), &credentials, kdf, cipher.as_deref())?;
+            println!("✅ {} shares combined, the mnemonic has been saved in vault '{}'.", shares.len(), vault.name);
+        }
+
+        Commands::Slip39 { slip39_command } => match slip39_command {
+            Slip39Commands::Split { name, passphrase, keyfile, group_threshold, groups, slip39_passphrase, iteration_exponent, entry } => {
+                let slip39_passphrase = slip39_passphrase.clone().unwrap_or_default();
+                slip39::check_parameters(*group_threshold, groups, *iteration_exponent, slip39_passphrase.expose().as_bytes())?;
+                let credentials = credentials(passphrase, keyfile.as_deref())?;
+                let vault = Vault::new(dir, name);
+                if !vault.exists() {
+                    return Err(VaultError::NotFound(vault.name.to_string()));
+                }
+                println!("💀 You have returned to this dungeon...");
+                if !run_puzzle_game() {
+                    return Err(VaultError::PuzzleFailed);
+                }
+                let ciphertext = vault.load()?;
+                let contents = decrypt_contents(&ciphertext, &credentials, &vault.name)?;
+                let entry = contents.mnemonic(entry.as_deref())?;
+                let (master_secret, language) = slip39::master_secret_from_bip39(&entry.secret)?;
+                let shares = slip39::generate(
+                    &master_secret,
+                    slip39_passphrase.expose().as_bytes(),
+                    *group_threshold,
+                    groups,
+                    true,
+                    *iteration_exponent,
+                )?;
+                println!(
+                    "\n🗝️  The mnemonic of vault '{}' has been split into SLIP-39 shares, {} of {} groups recover it:",
+                    vault.name,
+                    group_threshold,
+                    groups.len()
+                );
+                for (index, (group, members)) in groups.iter().zip(&shares).enumerate() {
+                    println!("\nGroup {} ({} of {} shares needed):", index + 1, group.threshold, group.count);
+                    for (member, share) in members.iter().enumerate() {
+                        println!("{}. {}", member + 1, share.as_str());
+                    }
+                }
+                if entry.seed_passphrase.is_some() {
+                    println!("\n⚠️  The BIP-39 passphrase is not part of the shares, back it up separately.");
+                }
+                if language != bip39::Language::English {
+                    println!(
+                        "\n⚠️  The mnemonic is in {}, which the shares do not record. Combine them with `--lang {}`, \
+                         any other language gives a different wallet.",
+                        mnemonic::language_name(language),
+                        mnemonic::language_code(language)
+                    );
+                }
+            }
+            Slip39Commands::Combine { name, passphrase, keyfile, shares, slip39_passphrase, lang, kdf, cipher } => {
+                let credentials = credentials(passphrase, keyfile.as_deref())?;
+                let language = mnemonic::parse_language(lang)?;
+                let shares = if shares.is_empty() { read_shares() } else { shares.clone() };
+                let shares: Vec<&str> = shares.iter().map(SecretString::expose).collect();
+                let slip39_passphrase = slip39_passphrase.clone().unwrap_or_default();
+                let master_secret = slip39::combine(&shares, slip39_passphrase.expose().as_bytes())?;
+                let mnemonic = slip39::bip39_from_master_secret(&master_secret, language)?;
+                let vault = create_new_vault(dir, name, &Contents::new(mnemonic, None), &credentials, kdf, cipher.as_deref())?;
+                println!("✅ {} SLIP-39 shares combined, the mnemonic has been saved in vault '{}'.", shares.len(), vault.name);
+            }
+        },
+
+        Commands::Fingerprint { name, passphrase, keyfile, account, entry } => {
+            let credentials = credentials(passphrase, keyfile.as_deref())?;
+            let (seed, has_seed_passphrase) = unlock_seed(dir, name, &credentials, entry.as_deref())?;
+            let master = wallet::ExtendedKey::master(seed.as_slice())?;
+            println!(
+                "Master fingerprint: {}{}",
+                master_fingerprint(seed.as_slice())?,
+                if has_seed_passphrase { " (with BIP-39 passphrase)" } else { "" }
+            );
+            for path in [format!("m/84'/0'/{}'", account), format!("m/44'/60'/{}'", account)] {
+                println!("xpub {}: {}", path, master.derive_path(&path)?.xpub());
+            }
+        }
+
+        Commands::Addresses { name, passphrase, keyfile, chain, count, account, entry } => {
+            let chain = wallet::Chain::parse(chain)?;
+            let credentials = credentials(passphrase, keyfile.as_deref())?;
+            let (seed, has_seed_passphrase) = unlock_seed(dir, name, &credentials, entry.as_deref())?;
+            println!(
+                "{} addresses of vault '{}'{}:",
+                chain.name(),
+                name,
+                if has_seed_passphrase { " (with BIP-39 passphrase)" } else { "" }
+            );
+            for (path, address) in wallet::addresses(seed.as_slice(), chain, *account, *count)? {
+                println!("{}  {}", path, address);
+            }
+        }
+
+        Commands::Entry { entry_command } => match entry_command {
+            EntryCommands::Add { name, label, kind, secret, seed_passphrase, force, passphrase, keyfile } => {
+                let kind = EntryKind::parse(kind)?;
+                let credentials = credentials(passphrase, keyfile.as_deref())?;
+                let vault = Vault::new(dir, name);
+                if !vault.exists() {
+                    return Err(VaultError::NotFound(vault.name.to_string()));
+                }
+                let secret = validate_entry_secret(kind, secret_or_stdin(secret, "Secret"), *force)?;
+                let mut entry = Entry::new(label, kind, secret)?;
+                entry.set_seed_passphrase(seed_passphrase.clone())?;
+                let label = entry.label.clone();
+                update_vault(dir, name, &credentials, |contents| contents.add(entry))?;
+                println!("✅ Added {} entry '{}' to vault '{}'.", kind.name(), label, name);
+            }
+            EntryCommands::Get { name, label, passphrase, keyfile, reveal_passphrase } => {
+                let credentials = credentials(passphrase, keyfile.as_deref())?;
+                let vault = Vault::new(dir, name);
+                if !vault.exists() {
+                    return Err(VaultError::NotFound(vault.name.to_string()));
+                }
+                println!("💀 You have returned to this dungeon...");
+                if !run_puzzle_game() {
+                    return Err(VaultError::PuzzleFailed);
+                }
+                let contents = decrypt_contents(&vault.load()?, &credentials, &vault.name)?;
+                print_entry(contents.get(label)?, *reveal_passphrase)?;
+            }
+            EntryCommands::Edit { name, label, secret, rename, force, passphrase, keyfile } => {
+                let credentials = credentials(passphrase, keyfile.as_deref())?;
+                update_vault(dir, name, &credentials, |contents| {
+                    let entry = contents.get_mut(label)?;
+                    if secret.is_some() || rename.is_none() {
+                        entry.secret = validate_entry_secret(entry.kind, secret_or_stdin(secret, "New secret"), *force)?;
+                        entry.touch();
+                    }
+                    match rename {
+                        Some(new_label) => contents.rename(label, new_label),
+                        None => Ok(()),
+                    }
+                })?;
+                println!("✅ Entry '{}' of vault '{}' has been updated.", rename.as_deref().unwrap_or(label), name);
+            }
+            EntryCommands::Remove { name, label, passphrase, keyfile } => {
+                let credentials = credentials(passphrase, keyfile.as_deref())?;
+                let removed = update_vault(dir, name, &credentials, |contents| contents.remove(label))?;
+                println!("✅ Removed {} entry '{}' from vault '{}'.", removed.kind.name(), removed.label, name);
+            }
+            EntryCommands::Ls { name, passphrase, keyfile } => {
+                let credentials = credentials(passphrase, keyfile.as_deref())?;
+                list_entries(dir, name, &credentials)?;
+            }
+        },
+
+        Commands::SeedPassphrase { seed_passphrase_command } => match seed_passphrase_command {
+            SeedPassphraseCommands::Set { name, passphrase, keyfile, seed_passphrase, entry } => {
+                if seed_passphrase.expose().is_empty() {
+                    return Err(VaultError::InvalidInput("The BIP-39 passphrase is empty, use `seed-passphrase clear` to remove it".into()));
+                }
+                let credentials = credentials(passphrase, keyfile.as_deref())?;
+                let fingerprint = update_seed_passphrase(dir, name, &credentials, entry.as_deref(), Some(seed_passphrase))?;
+                println!("✅ The BIP-39 passphrase of vault '{}' has been stored.", name);
+                println!("Master fingerprint: {} (check it against your wallet)", fingerprint);
+            }
+            SeedPassphraseCommands::Clear { name, passphrase, keyfile, entry } => {
+                let credentials = credentials(passphrase, keyfile.as_deref())?;
+                let fingerprint = update_seed_passphrase(dir, name, &credentials, entry.as_deref(), None)?;
+                println!("✅ The BIP-39 passphrase of vault '{}' has been removed.", name);
+                println!("Master fingerprint: {}", fingerprint);
+            }
+        },
+
+        Commands::Rekey { name, passphrase, new_passphrase, keyfile, new_keyfile, no_keyfile, kdf, cipher, shred_old_backups } => {
+            let old = credentials(passphrase, keyfile.as_deref())?;
+            // 默认沿用原来的密钥文件
+            let new_keyfile = match (new_keyfile, no_keyfile) {
+                (Some(path), _) => Some(path.as_path()),
+                (None, true) => None,
+                (None, false) => keyfile.as_deref(),
+            };
+            let new = credentials(new_passphrase, new_keyfile)?;
+            rekey_vault(dir, name, &old, &new, kdf, cipher.as_deref(), *shred_old_backups)?;
+        }
+
+        Commands::Slot { slot_command } => match slot_command {
+            SlotCommands::Add { name, passphrase, keyfile, new_passphrase, new_keyfile, label, kdf } => {
+                let existing = credentials(passphrase, keyfile.as_deref())?;
+                let new = credentials(new_passphrase, new_keyfile.as_deref())?;
+                let kdf = resolve_kdf(kdf)?;
+                let vault = Vault::new(dir, name);
+                let _lock = vault.lock()?;
+                let ciphertext = vault.load()?;
+                let (new_ciphertext, id) = storage::add_key_slot(&ciphertext, &vault.name, &existing, &new, label, kdf)?;
+                vault.save(&new_ciphertext)?;
+                println!("✅ Added key slot {} to vault '{}' ({}).", id, vault.name, kdf);
+            }
+            SlotCommands::List { name } => {
+                list_key_slots(dir, name)?;
+            }
+            SlotCommands::Revoke { name, id, passphrase, keyfile, shred_old_backups } => {
+                let credentials = credentials(passphrase, keyfile.as_deref())?;
+                let vault = Vault::new(dir, name);
+                let _lock = vault.lock()?;
+                let ciphertext = vault.load()?;
+                let new_ciphertext = storage::revoke_key_slot(&ciphertext, &vault.name, &credentials, *id)?;
+                vault.save(&new_ciphertext)?;
+                println!("✅ Key slot {} of vault '{}' has been revoked.", id, vault.name);
+                prune_stale_backups(&vault, &new_ciphertext, *shred_old_backups)?;
+            }
+        },
+
+        Commands::KdfCalibrate { target_ms, kdf } => {
+            calibrate_kdfs(*target_ms, kdf)?;
+        }
+
         Commands::Totp { totp_command } => {
             match totp_command {
                 TotpCommands::Generate { account, issuer, secret, algorithm, digits, step } => {
-                    let alg = match parse_algorithm(algorithm) {
-                        Ok(alg) => alg,
-                        Err(e) => {
-                            eprintln!("❌ {}", e);
-                            return;
-                        }
-                    };
+                    let alg = parse_algorithm(algorithm)?;
 
                     // 优先使用命令行参数，否则从环境变量获取默认值
-                    let secret_key = secret.clone().unwrap_or_else(|| {
-                        std::env::var("VAULT_TOTP_SECRET").unwrap_or_else(|_| {
+                    let secret_key = match secret {
+                        Some(secret) => secret.clone(),
+                        None => std::env::var("VAULT_TOTP_SECRET").map(SecretString::new).unwrap_or_else(|_| {
                             let generated = generate_secret();
-                            println!("🔑 Generated secret: {}", generated);
+                            println!("🔑 Generated secret: {}", generated.expose());
                             generated
-                        })
-                    });
-
-                    let account_name = account.clone().unwrap_or_else(|| {
-                        std::env::var("VAULT_TOTP_ACCOUNT").unwrap_or_else(|_| {
-                            eprintln!("❌ TOTP account not provided. Please set VAULT_TOTP_ACCOUNT environment variable or use --account parameter.");
-                            std::process::exit(1);
-                        })
-                    });
+                        }),
+                    };
+
+                    let account_name = match account {
+                        Some(account) => account.clone(),
+                        None => std::env::var("VAULT_TOTP_ACCOUNT").map_err(|_| {
+                            VaultError::InvalidInput(
+                                "TOTP account not provided. Please set VAULT_TOTP_ACCOUNT environment variable or use --account parameter.".into(),
+                            )
+                        })?,
+                    };
                     let issuer_name = issuer.clone().or_else(|| std::env::var("VAULT_TOTP_ISSUER").ok());
 
                     let config = TotpConfig {
@@ -410,35 +2142,31 @@ fn main() {
                         account: account_name,
                     };
 
-                    match generate_totp_secret(&config) {
-                        Ok(_) => println!("✅ TOTP configuration has been generated successfully!"),
-                        Err(e) => eprintln!("❌ Failed to generate TOTP: {}", e),
-                    }
+                    generate_totp_secret(&config)?;
+                    println!("✅ TOTP configuration has been generated successfully!");
                 }
 
                 TotpCommands::Code { account, issuer, secret, algorithm, digits, step } => {
-                    let alg = match parse_algorithm(algorithm) {
-                        Ok(alg) => alg,
-                        Err(e) => {
-                            eprintln!("❌ {}", e);
-                            return;
-                        }
-                    };
+                    let alg = parse_algorithm(algorithm)?;
 
                     // 优先使用命令行参数，否则从环境变量获取默认值
-                    let secret_key = secret.clone().unwrap_or_else(|| {
-                        std::env::var("VAULT_TOTP_SECRET").unwrap_or_else(|_| {
-                            eprintln!("❌ TOTP secret not provided. Please set VAULT_TOTP_SECRET environment variable or use --secret parameter.");
-                            std::process::exit(1);
-                        })
-                    });
-
-                    let account_name = account.clone().unwrap_or_else(|| {
-                        std::env::var("VAULT_TOTP_ACCOUNT").unwrap_or_else(|_| {
-                            eprintln!("❌ TOTP account not provided. Please set VAULT_TOTP_ACCOUNT environment variable or use --account parameter.");
-                            std::process::exit(1);
-                        })
-                    });
+                    let secret_key = match secret {
+                        Some(secret) => secret.clone(),
+                        None => std::env::var("VAULT_TOTP_SECRET").map(SecretString::new).map_err(|_| {
+                            VaultError::InvalidInput(
+                                "TOTP secret not provided. Please set VAULT_TOTP_SECRET environment variable or use --secret parameter.".into(),
+                            )
+                        })?,
+                    };
+
+                    let account_name = match account {
+                        Some(account) => account.clone(),
+                        None => std::env::var("VAULT_TOTP_ACCOUNT").map_err(|_| {
+                            VaultError::InvalidInput(
+                                "TOTP account not provided. Please set VAULT_TOTP_ACCOUNT environment variable or use --account parameter.".into(),
+                            )
+                        })?,
+                    };
 
                     let issuer_name = issuer.clone().or_else(|| std::env::var("VAULT_TOTP_ISSUER").ok());
 
@@ -452,12 +2180,21 @@ fn main() {
                         account: account_name,
                     };
 
-                    match get_totp_code(&config) {
-                        Ok(code) => println!("🔐 Current verification code: {}", code),
-                        Err(e) => eprintln!("❌ Failed to get verification code: {}", e),
-                    }
+                    let code = get_totp_code(&config)?;
+                    println!("🔐 Current verification code: {}", code);
                 }
             }
         }
     }
-}
\ No newline at end of file
+    Ok(())
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    let result = vault::resolve_dir(cli.vault_dir.as_deref()).and_then(|dir| run(&cli.command, &dir));
+    if let Err(e) = result {
+        eprintln!("❌ {}", e);
+        std::process::exit(e.exit_code());
+    }
+}
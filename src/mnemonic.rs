@@ -0,0 +1,229 @@
+// vault_rpg/src/mnemonic.rs
+
+use bip39::{Language, Mnemonic};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use unicode_normalization::UnicodeNormalization;
+use zeroize::Zeroizing;
+
+use crate::error::VaultError;
+use crate::secret::SecretString;
+
+/// BIP-39 允许的单词数
+pub const WORD_COUNTS: [usize; 5] = [12, 15, 18, 21, 24];
+
+/// 每个拼错的单词最多给出的建议数
+const MAX_SUGGESTIONS: usize = 3;
+
+/// 语言代码和显示名称
+const LANGUAGES: [(Language, &str, &str); 10] = [
+    (Language::English, "en", "English"),
+    (Language::SimplifiedChinese, "zh", "Chinese (Simplified)"),
+    (Language::TraditionalChinese, "zh-hant", "Chinese (Traditional)"),
+    (Language::Japanese, "ja", "Japanese"),
+    (Language::Korean, "ko", "Korean"),
+    (Language::Spanish, "es", "Spanish"),
+    (Language::French, "fr", "French"),
+    (Language::Italian, "it", "Italian"),
+    (Language::Portuguese, "pt", "Portuguese"),
+    (Language::Czech, "cs", "Czech"),
+];
+
+pub fn language_name(language: Language) -> &'static str {
+    LANGUAGES
+        .iter()
+        .find(|(lang, _, _)| *lang == language)
+        .map_or("Unknown", |(_, _, name)| name)
+}
+
+/// 语言代码，与 `parse_language` 接受的写法一致
+pub fn language_code(language: Language) -> &'static str {
+    LANGUAGES
+        .iter()
+        .find(|(lang, _, _)| *lang == language)
+        .map_or("en", |(_, code, _)| code)
+}
+
+/// 解析语言代码，如 `en`、`zh`、`zh-hant`、`ja`
+pub fn parse_language(code: &str) -> Result<Language, VaultError> {
+    let code = code.to_lowercase().replace('_', "-");
+    let code = match code.as_str() {
+        "zh-hans" | "zh-cn" => "zh",
+        "zh-tw" | "zh-hk" => "zh-hant",
+        other => other,
+    };
+    LANGUAGES
+        .iter()
+        .find(|(_, lang_code, _)| *lang_code == code)
+        .map(|(language, _, _)| *language)
+        .ok_or_else(|| {
+            let codes: Vec<&str> = LANGUAGES.iter().map(|(_, code, _)| *code).collect();
+            VaultError::InvalidInput(format!("Unsupported language '{}', expected one of: {}", code, codes.join(", ")))
+        })
+}
+
+/// 用系统随机数生成新的助记词
+///
+/// 提供骰子点数（1-6 组成的字符串）时，与系统随机数一起经 SHA-256 混合，
+/// 任意一方可信即可保证熵的质量。
+pub fn generate(word_count: usize, language: Language, dice: Option<&str>) -> Result<Mnemonic, VaultError> {
+    if !WORD_COUNTS.contains(&word_count) {
+        return Err(VaultError::InvalidInput(format!(
+            "Word count must be one of 12, 15, 18, 21 or 24, not {}",
+            word_count
+        )));
+    }
+    let mut entropy = Zeroizing::new([0u8; 32]);
+    rand::thread_rng().fill_bytes(entropy.as_mut());
+
+    if let Some(dice) = dice {
+        let rolls: String = dice.chars().filter(|c| !c.is_whitespace()).collect();
+        if rolls.is_empty() || !rolls.chars().all(|c| ('1'..='6').contains(&c)) {
+            return Err(VaultError::InvalidInput("Dice rolls must only contain the digits 1 to 6".into()));
+        }
+        let mut hasher = Sha256::new();
+        hasher.update(entropy.as_slice());
+        hasher.update(b"dice:");
+        hasher.update(rolls.as_bytes());
+        entropy.copy_from_slice(&hasher.finalize());
+    }
+
+    let entropy_len = word_count * 4 / 3;
+    Mnemonic::from_entropy_in(language, &entropy[..entropy_len])
+        .map_err(|e| VaultError::InvalidInput(format!("Failed to generate mnemonic: {}", e)))
+}
+
+/// 按 BIP-39 校验助记词（单词数、词表、校验和），自动识别语言
+///
+/// 大小写、多余的空白和 Unicode 组合形式（键盘输入多为 NFC，词表为 NFKD）不影响结果；
+/// 词表不匹配时给出拼写建议。
+pub fn parse(mnemonic: &SecretString) -> Result<Mnemonic, VaultError> {
+    let normalized = SecretString::new(mnemonic.expose().to_lowercase().nfkd().collect());
+    let words: Vec<&str> = normalized.expose().split_whitespace().collect();
+    if !WORD_COUNTS.contains(&words.len()) {
+        return Err(VaultError::InvalidMnemonic(format!(
+            "{} words given, BIP-39 mnemonics have 12, 15, 18, 21 or 24",
+            words.len()
+        )));
+    }
+
+    // 简体和繁体中文的词表有大量重合，依次尝试所有包含全部单词的语言，以校验和为准
+    let candidates: Vec<Language> = Language::ALL
+        .iter()
+        .copied()
+        .filter(|language| words.iter().all(|word| language.find_word(word).is_some()))
+        .collect();
+    if let Some(language) = candidates.first() {
+        return candidates
+            .iter()
+            .find_map(|language| Mnemonic::parse_in(*language, normalized.expose()).ok())
+            .ok_or_else(|| {
+                VaultError::InvalidMnemonic(format!(
+                    "checksum mismatch, all words are in the {} wordlist but one of them is wrong or out of order",
+                    language_name(*language)
+                ))
+            });
+    }
+
+    // 单词数相同时取 `Language::ALL` 中靠前的语言（英语优先），max_by_key 本身会返回最后一个最大值
+    let language = Language::ALL
+        .iter()
+        .rev()
+        .copied()
+        .max_by_key(|language| words.iter().filter(|word| language.find_word(word).is_some()).count())
+        .unwrap_or(Language::English);
+    let unknown: Vec<String> = words
+        .iter()
+        .enumerate()
+        .filter(|(_, word)| language.find_word(word).is_none())
+        .map(|(index, word)| {
+            let suggestions = suggest(language, word);
+            if suggestions.is_empty() {
+                format!("word {} '{}'", index + 1, word)
+            } else {
+                format!("word {} '{}' (did you mean {}?)", index + 1, word, suggestions.join(", "))
+            }
+        })
+        .collect();
+    Err(VaultError::InvalidMnemonic(format!(
+        "words missing from the {} wordlist: {}",
+        language_name(language),
+        unknown.join("; ")
+    )))
+}
+
+/// 为拼错的单词找出词表中最接近的几个词：前 4 个字母唯一确定的词优先，其次按编辑距离
+fn suggest(language: Language, word: &str) -> Vec<&'static str> {
+    let chars: Vec<char> = word.chars().collect();
+    // 中文等单字词表的编辑距离没有意义
+    if chars.len() < 3 {
+        return Vec::new();
+    }
+
+    let mut suggestions: Vec<(usize, &'static str)> = Vec::new();
+    if chars.len() >= 4 {
+        let prefix: String = chars[..4].iter().collect();
+        if let [only] = language.words_by_prefix(&prefix) {
+            suggestions.push((0, *only));
+        }
+    }
+    let max_distance = if chars.len() <= 4 { 1 } else { 2 };
+    for candidate in language.word_list() {
+        let distance = edit_distance(&chars, &candidate.chars().collect::<Vec<char>>());
+        if distance <= max_distance && !suggestions.iter().any(|(_, word)| word == candidate) {
+            suggestions.push((distance, candidate));
+        }
+    }
+    suggestions.sort_by_key(|(distance, _)| *distance);
+    suggestions.into_iter().take(MAX_SUGGESTIONS).map(|(_, word)| word).collect()
+}
+
+/// 编辑距离，相邻字母交换计为一次
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut rows = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (rows[i - 1][j] + 1).min(rows[i][j - 1] + 1).min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_nfc_input_for_accented_wordlists() {
+        let mnemonic = Mnemonic::from_entropy_in(Language::Spanish, &[0x1f; 16]).unwrap().to_string();
+        let composed: String = mnemonic.nfc().collect();
+        assert_ne!(composed, mnemonic, "the test mnemonic should contain accented words");
+
+        let parsed = parse(&SecretString::new(composed.to_uppercase())).unwrap();
+        assert_eq!(parsed.language(), Language::Spanish);
+        assert_eq!(parsed.to_string(), mnemonic);
+    }
+
+    #[test]
+    fn prefers_english_when_wordlists_tie() {
+        // "abandon" 同时在英语和法语词表中，拼错的最后一个词应按英语给出建议
+        let typo = SecretString::new(format!("{} abaut", "abandon ".repeat(11)));
+        let message = match parse(&typo) {
+            Err(VaultError::InvalidMnemonic(message)) => message,
+            other => panic!("expected an invalid mnemonic error, got {:?}", other.map(|m| m.to_string())),
+        };
+        assert!(message.contains("English"), "{}", message);
+        assert!(message.contains("about"), "{}", message);
+    }
+}
@@ -2,6 +2,7 @@
 
 use std::io::{self, Write};
 
+use crate::secret::SecretString;
 use crate::totp::{TotpConfig, get_totp_code};
 
 pub fn run_puzzle_game() -> bool {
@@ -20,7 +21,7 @@ pub fn run_puzzle_game() -> bool {
             
             // 从环境变量获取 TOTP 配置
             let secret = match std::env::var("VAULT_TOTP_SECRET") {
-                Ok(secret) => secret,
+                Ok(secret) => SecretString::new(secret),
                 Err(_) => {
                     println!("❌ TOTP secret not configured. Please set VAULT_TOTP_SECRET environment variable.");
                     return false;
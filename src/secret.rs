@@ -0,0 +1,54 @@
+// vault_rpg/src/secret.rs
+
+use std::fmt;
+use std::str::FromStr;
+
+use zeroize::Zeroize;
+
+/// 口令、助记词、TOTP 密钥等敏感字符串
+///
+/// 释放时清零内存，`Debug` 不输出内容，也不实现 `Display`，只能通过 `expose` 显式取出。
+#[derive(Clone, Default)]
+pub struct SecretString(String);
+
+impl SecretString {
+    pub fn new(value: String) -> Self {
+        SecretString(value)
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for SecretString {
+    fn from(value: &str) -> Self {
+        SecretString(value.to_string())
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(value: String) -> Self {
+        SecretString(value)
+    }
+}
+
+impl FromStr for SecretString {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(SecretString::from(s))
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretString(***)")
+    }
+}
+
+impl Drop for SecretString {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
@@ -0,0 +1,338 @@
+// vault_rpg/src/shamir.rs
+
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::str::FromStr;
+use zeroize::Zeroizing;
+
+use crate::error::VaultError;
+
+/// 份额文本的前缀，同时标识编码版本
+const SHARE_PREFIX: &str = "vrpg-share1";
+const SET_ID_LEN: usize = 4;
+const CHECKSUM_LEN: usize = 4;
+/// 拆分前在秘密前面附加的摘要长度，合并后用来确认恢复结果
+const DIGEST_LEN: usize = 4;
+
+/// GF(2^8) 运算，约化多项式 x^8 + x^4 + x^3 + x + 1（与 AES 相同）
+struct Gf256 {
+    exp: [u8; 255],
+    log: [u8; 256],
+}
+
+const GF256: Gf256 = Gf256::new();
+
+impl Gf256 {
+    const fn new() -> Self {
+        let mut exp = [0u8; 255];
+        let mut log = [0u8; 256];
+        let mut value: u16 = 1;
+        let mut i = 0;
+        while i < 255 {
+            exp[i] = value as u8;
+            log[value as usize] = i as u8;
+            // 乘以生成元 3
+            value ^= value << 1;
+            if value & 0x100 != 0 {
+                value ^= 0x11b;
+            }
+            i += 1;
+        }
+        Gf256 { exp, log }
+    }
+
+    fn mul(&self, a: u8, b: u8) -> u8 {
+        if a == 0 || b == 0 {
+            return 0;
+        }
+        self.exp[(self.log[a as usize] as usize + self.log[b as usize] as usize) % 255]
+    }
+
+    fn div(&self, a: u8, b: u8) -> u8 {
+        debug_assert!(b != 0);
+        if a == 0 {
+            return 0;
+        }
+        self.exp[(self.log[a as usize] as usize + 255 - self.log[b as usize] as usize) % 255]
+    }
+}
+
+/// 检查份额数量和门限，最多 254 份（x = 0 保留给秘密本身）
+pub fn check_parameters(threshold: u8, count: u8) -> Result<(), VaultError> {
+    if threshold == 0 || threshold > count {
+        return Err(VaultError::InvalidInput(format!(
+            "Threshold must be between 1 and the number of shares ({})",
+            count
+        )));
+    }
+    if count == u8::MAX {
+        return Err(VaultError::InvalidInput("At most 254 shares are supported".into()));
+    }
+    Ok(())
+}
+
+/// 一个份额点：x 坐标和各字节多项式在该处的值
+pub type Point = (u8, Zeroizing<Vec<u8>>);
+
+/// 按字节把 `secret` 拆成 `count` 份，任意 `threshold` 份可以恢复。份额的 x 坐标为 1..=count
+pub fn split_secret(secret: &[u8], threshold: u8, count: u8) -> Result<Vec<Point>, VaultError> {
+    check_parameters(threshold, count)?;
+
+    let mut rng = rand::thread_rng();
+    let mut coefficients = Zeroizing::new(vec![0u8; threshold as usize - 1]);
+    let mut shares: Vec<Point> = (1..=count)
+        .map(|x| (x, Zeroizing::new(Vec::with_capacity(secret.len()))))
+        .collect();
+    for &byte in secret {
+        rng.fill_bytes(&mut coefficients);
+        for (x, data) in shares.iter_mut() {
+            // Horner 法求多项式在 x 处的值，常数项为秘密字节
+            let y = coefficients
+                .iter()
+                .rev()
+                .fold(0u8, |acc, &c| GF256.mul(acc, *x) ^ c);
+            data.push(GF256.mul(y, *x) ^ byte);
+        }
+    }
+    Ok(shares)
+}
+
+/// 拉格朗日插值求多项式在 `x` 处的值，各点的 x 坐标必须互不相同，数据长度必须一致
+pub fn interpolate(points: &[(u8, &[u8])], x: u8) -> Zeroizing<Vec<u8>> {
+    let len = points.first().map_or(0, |(_, data)| data.len());
+    let mut result = Zeroizing::new(vec![0u8; len]);
+    for (i, (xi, data)) in points.iter().enumerate() {
+        if *xi == x {
+            result.copy_from_slice(data);
+            return result;
+        }
+        let mut basis = 1u8;
+        for (j, (xj, _)) in points.iter().enumerate() {
+            if i != j {
+                basis = GF256.mul(basis, GF256.div(x ^ xj, xi ^ xj));
+            }
+        }
+        for (out, &y) in result.iter_mut().zip(data.iter()) {
+            *out ^= GF256.mul(basis, y);
+        }
+    }
+    result
+}
+
+/// 一份可打印的秘密份额
+///
+/// 文本格式为 `vrpg-share1-<set id>-<threshold>-<index>-<data>-<checksum>`，
+/// 数据和校验和为十六进制，校验和是前面所有内容 SHA-256 的前 4 字节。
+pub struct Share {
+    pub set_id: [u8; SET_ID_LEN],
+    pub threshold: u8,
+    pub index: u8,
+    data: Zeroizing<Vec<u8>>,
+}
+
+impl Share {
+    fn body(&self) -> Zeroizing<String> {
+        Zeroizing::new(format!(
+            "{}-{}-{}-{}-{}",
+            SHARE_PREFIX,
+            hex(&self.set_id).as_str(),
+            self.threshold,
+            self.index,
+            hex(&self.data).as_str()
+        ))
+    }
+}
+
+impl fmt::Display for Share {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let body = self.body();
+        write!(f, "{}-{}", body.as_str(), hex(&checksum(&body)).as_str())
+    }
+}
+
+impl fmt::Debug for Share {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Share")
+            .field("set_id", &hex(&self.set_id).as_str())
+            .field("threshold", &self.threshold)
+            .field("index", &self.index)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FromStr for Share {
+    type Err = VaultError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let invalid = |msg: &str| VaultError::Share(msg.to_string());
+        let (body, sum) = s.rsplit_once('-').ok_or_else(|| invalid("not a vault_rpg share"))?;
+        let parts: Vec<&str> = body.split('-').collect();
+        if parts.len() != 6 || format!("{}-{}", parts[0], parts[1]) != SHARE_PREFIX {
+            return Err(invalid("not a vault_rpg share"));
+        }
+        let expected = checksum(body);
+        if unhex(sum).as_deref().map(|sum| sum.as_slice()) != Some(expected.as_slice()) {
+            return Err(invalid("checksum mismatch, the share was mistyped or damaged"));
+        }
+
+        let set_id = unhex(parts[2])
+            .and_then(|id| <[u8; SET_ID_LEN]>::try_from(id.as_slice()).ok())
+            .ok_or_else(|| invalid("bad set id"))?;
+        let threshold = parts[3].parse().map_err(|_| invalid("bad threshold"))?;
+        let index = parts[4].parse().map_err(|_| invalid("bad share index"))?;
+        let data = unhex(parts[5]).ok_or_else(|| invalid("bad share data"))?;
+        if threshold == 0 || index == 0 || data.len() <= DIGEST_LEN {
+            return Err(invalid("share is malformed"));
+        }
+        Ok(Share { set_id, threshold, index, data })
+    }
+}
+
+/// 把秘密拆成 `count` 份，同一次拆分的份额共享一个随机的集合编号
+pub fn split(secret: &[u8], threshold: u8, count: u8) -> Result<Vec<Share>, VaultError> {
+    let mut payload = Zeroizing::new(Sha256::digest(secret)[..DIGEST_LEN].to_vec());
+    payload.extend_from_slice(secret);
+
+    let mut set_id = [0u8; SET_ID_LEN];
+    rand::thread_rng().fill_bytes(&mut set_id);
+    Ok(split_secret(&payload, threshold, count)?
+        .into_iter()
+        .map(|(index, data)| Share { set_id, threshold, index, data })
+        .collect())
+}
+
+/// 合并份额恢复秘密，拒绝来自不同集合、数量不足或重复的份额
+pub fn combine(shares: &[Share]) -> Result<Zeroizing<Vec<u8>>, VaultError> {
+    let first = shares.first().ok_or_else(|| VaultError::Share("no shares given".into()))?;
+    for share in shares {
+        if share.set_id != first.set_id || share.threshold != first.threshold || share.data.len() != first.data.len() {
+            return Err(VaultError::Share(format!(
+                "share {} does not belong to the same set as share {}",
+                share.index, first.index
+            )));
+        }
+    }
+    let mut indices: Vec<u8> = shares.iter().map(|share| share.index).collect();
+    indices.sort_unstable();
+    if let Some(pair) = indices.windows(2).find(|pair| pair[0] == pair[1]) {
+        return Err(VaultError::Share(format!("share {} was given more than once", pair[0])));
+    }
+    if shares.len() < first.threshold as usize {
+        return Err(VaultError::Share(format!(
+            "{} of {} required shares given",
+            shares.len(),
+            first.threshold
+        )));
+    }
+
+    let points: Vec<(u8, &[u8])> = shares
+        .iter()
+        .take(first.threshold as usize)
+        .map(|share| (share.index, share.data.as_slice()))
+        .collect();
+    let payload = interpolate(&points, 0);
+    let (digest, secret) = payload.split_at(DIGEST_LEN);
+    if Sha256::digest(secret)[..DIGEST_LEN] != *digest {
+        return Err(VaultError::Share("shares do not reconstruct a valid secret".into()));
+    }
+    Ok(Zeroizing::new(secret.to_vec()))
+}
+
+fn checksum(body: &str) -> [u8; CHECKSUM_LEN] {
+    let mut sum = [0u8; CHECKSUM_LEN];
+    sum.copy_from_slice(&Sha256::digest(body.as_bytes())[..CHECKSUM_LEN]);
+    sum
+}
+
+fn hex(bytes: &[u8]) -> Zeroizing<String> {
+    Zeroizing::new(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+fn unhex(text: &str) -> Option<Zeroizing<Vec<u8>>> {
+    if !text.len().is_multiple_of(2) || !text.is_ascii() {
+        return None;
+    }
+    let bytes = (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    Some(Zeroizing::new(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &[u8] = b"sixteen byte key and some more bytes";
+
+    fn parse(share: &Share) -> Share {
+        share.to_string().parse().unwrap()
+    }
+
+    #[test]
+    fn any_threshold_subset_recovers_the_secret() {
+        let shares = split(SECRET, 3, 5).unwrap();
+        assert_eq!(shares.len(), 5);
+        for a in 0..5 {
+            for b in a + 1..5 {
+                for c in b + 1..5 {
+                    let subset = [parse(&shares[c]), parse(&shares[a]), parse(&shares[b])];
+                    assert_eq!(combine(&subset).unwrap().as_slice(), SECRET);
+                }
+            }
+        }
+        // 多于门限的份额也可以
+        let all: Vec<Share> = shares.iter().map(parse).collect();
+        assert_eq!(combine(&all).unwrap().as_slice(), SECRET);
+
+        let single = split(SECRET, 1, 1).unwrap();
+        assert_eq!(combine(&single).unwrap().as_slice(), SECRET);
+    }
+
+    #[test]
+    fn fewer_than_threshold_shares_fail() {
+        let shares = split(SECRET, 3, 5).unwrap();
+        assert!(matches!(combine(&shares[..2]), Err(VaultError::Share(_))));
+        assert!(matches!(combine(&[]), Err(VaultError::Share(_))));
+    }
+
+    #[test]
+    fn rejects_duplicate_and_foreign_shares() {
+        let shares = split(SECRET, 2, 3).unwrap();
+        let duplicate = [parse(&shares[0]), parse(&shares[0])];
+        assert!(matches!(combine(&duplicate), Err(VaultError::Share(message)) if message.contains("more than once")));
+
+        let other = split(SECRET, 2, 3).unwrap();
+        let mixed = [parse(&shares[0]), parse(&other[1])];
+        assert!(matches!(combine(&mixed), Err(VaultError::Share(_))));
+    }
+
+    #[test]
+    fn rejects_damaged_share_text() {
+        let text = split(SECRET, 2, 3).unwrap()[0].to_string();
+        let (body, sum) = text.rsplit_once('-').unwrap();
+        let flipped = if sum.starts_with('0') { "1" } else { "0" };
+        assert!(format!("{}-{}{}", body, flipped, &sum[1..]).parse::<Share>().is_err());
+        assert!(text.replacen(SHARE_PREFIX, "vrpg-share9", 1).parse::<Share>().is_err());
+        assert!("not a share".parse::<Share>().is_err());
+        assert!(format!("  {}\n", text).parse::<Share>().is_ok());
+    }
+
+    #[test]
+    fn checks_parameters() {
+        assert!(check_parameters(0, 3).is_err());
+        assert!(check_parameters(4, 3).is_err());
+        assert!(check_parameters(2, u8::MAX).is_err());
+        assert!(check_parameters(254, 254).is_ok());
+    }
+
+    #[test]
+    fn interpolation_hits_every_point() {
+        let points = split_secret(&[0x42, 0x00, 0xff], 3, 4).unwrap();
+        let borrowed: Vec<(u8, &[u8])> = points.iter().map(|(x, data)| (*x, data.as_slice())).collect();
+        assert_eq!(interpolate(&borrowed[..3], 0).as_slice(), &[0x42, 0x00, 0xff]);
+        assert_eq!(interpolate(&borrowed[..3], 4).as_slice(), borrowed[3].1);
+    }
+}
@@ -0,0 +1,540 @@
+// vault_rpg/src/slip39.rs
+
+use bip39::Language;
+use hmac::{Hmac, Mac};
+use pbkdf2::pbkdf2_hmac;
+use rand::RngCore;
+use sha2::Sha256;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::LazyLock;
+use zeroize::Zeroizing;
+
+use crate::error::VaultError;
+use crate::secret::SecretString;
+use crate::shamir::{Point, interpolate};
+
+/// SLIP-39 官方词表，共 1024 个单词，按字母排序
+static WORDLIST: LazyLock<Vec<&'static str>> = LazyLock::new(|| include_str!("slip39_wordlist.txt").lines().collect());
+
+const RADIX_BITS: usize = 10;
+const ID_BITS: usize = 15;
+const CHECKSUM_WORDS: usize = 3;
+const METADATA_WORDS: usize = 4;
+const MIN_MNEMONIC_WORDS: usize = METADATA_WORDS + CHECKSUM_WORDS + 13;
+const MIN_SECRET_LEN: usize = 16;
+const MAX_SHARE_COUNT: u8 = 16;
+const MAX_ITERATION_EXPONENT: u8 = 15;
+const DIGEST_LEN: usize = 4;
+const DIGEST_INDEX: u8 = 254;
+const SECRET_INDEX: u8 = 255;
+const BASE_ITERATION_COUNT: u32 = 10_000;
+const ROUND_COUNT: u8 = 4;
+
+/// 新生成份额使用的默认迭代指数（PBKDF2 共 20000 次）
+pub const DEFAULT_ITERATION_EXPONENT: u8 = 1;
+
+/// 一个成员组的门限，写作 `3of5`
+#[derive(Debug, Clone, Copy)]
+pub struct GroupSpec {
+    pub threshold: u8,
+    pub count: u8,
+}
+
+impl FromStr for GroupSpec {
+    type Err = VaultError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || VaultError::InvalidInput(format!("Invalid group '{}', expected e.g. 3of5", s));
+        let text = s.trim().to_lowercase();
+        let (threshold, count) = text.split_once("of").ok_or_else(invalid)?;
+        Ok(GroupSpec {
+            threshold: threshold.trim_end_matches('-').parse().map_err(|_| invalid())?,
+            count: count.trim_start_matches('-').parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// 一份解码后的 SLIP-39 份额
+struct Share {
+    id: u16,
+    extendable: bool,
+    iteration_exponent: u8,
+    group_index: u8,
+    group_threshold: u8,
+    group_count: u8,
+    member_index: u8,
+    member_threshold: u8,
+    value: Zeroizing<Vec<u8>>,
+}
+
+impl Share {
+    fn to_mnemonic(&self) -> Zeroizing<String> {
+        let mut words = Zeroizing::new(vec![
+            self.id >> 5,
+            ((self.id & 0x1f) << 5) | ((self.extendable as u16) << 4) | self.iteration_exponent as u16,
+            ((self.group_index as u16) << 6) | (((self.group_threshold - 1) as u16) << 2) | ((self.group_count - 1) as u16 >> 2),
+            ((((self.group_count - 1) & 0x03) as u16) << 8)
+                | ((self.member_index as u16) << 4)
+                | (self.member_threshold - 1) as u16,
+        ]);
+        words.extend(bytes_to_words(&self.value).iter());
+        let checksum = rs1024_create_checksum(customization(self.extendable), &words);
+        words.extend(checksum);
+        Zeroizing::new(words.iter().map(|&w| WORDLIST[w as usize]).collect::<Vec<_>>().join(" "))
+    }
+
+    fn from_mnemonic(mnemonic: &str) -> Result<Self, VaultError> {
+        let invalid = |msg: &str| VaultError::Share(msg.to_string());
+        let words = Zeroizing::new(mnemonic
+            .split_whitespace()
+            .map(|word| {
+                let word = word.to_lowercase();
+                WORDLIST
+                    .binary_search(&word.as_str())
+                    .map(|index| index as u16)
+                    .map_err(|_| VaultError::Share(format!("'{}' is not a SLIP-39 word", word)))
+            })
+            .collect::<Result<Vec<u16>, VaultError>>()?);
+        if words.len() < MIN_MNEMONIC_WORDS {
+            return Err(invalid("mnemonic is too short"));
+        }
+        let padding_bits = (RADIX_BITS * (words.len() - METADATA_WORDS - CHECKSUM_WORDS)) % 16;
+        if padding_bits > 8 {
+            return Err(invalid("mnemonic has an invalid length"));
+        }
+
+        let extendable = (words[1] >> 4) & 1 == 1;
+        if rs1024_polymod(customization(extendable), &words) != 1 {
+            return Err(invalid("checksum mismatch, the mnemonic was mistyped or damaged"));
+        }
+
+        let value = words_to_bytes(&words[METADATA_WORDS..words.len() - CHECKSUM_WORDS], padding_bits)
+            .ok_or_else(|| invalid("mnemonic has invalid padding"))?;
+        if value.len() < MIN_SECRET_LEN || !value.len().is_multiple_of(2) {
+            return Err(invalid("master secret has an invalid length"));
+        }
+
+        let group_count = ((words[2] & 0x03) << 2 | words[3] >> 8) as u8 + 1;
+        Ok(Share {
+            id: (words[0] << 5) | (words[1] >> 5),
+            extendable,
+            iteration_exponent: (words[1] & 0x0f) as u8,
+            group_index: (words[2] >> 6) as u8,
+            group_threshold: ((words[2] >> 2) & 0x0f) as u8 + 1,
+            group_count,
+            member_index: ((words[3] >> 4) & 0x0f) as u8,
+            member_threshold: (words[3] & 0x0f) as u8 + 1,
+            value,
+        })
+    }
+}
+
+fn customization(extendable: bool) -> &'static [u8] {
+    if extendable { b"shamir_extendable" } else { b"shamir" }
+}
+
+fn rs1024_polymod(customization: &[u8], words: &[u16]) -> u32 {
+    const GEN: [u32; 10] = [
+        0xe0e040, 0x1c1c080, 0x3838100, 0x7070200, 0xe0e0009, 0x1c0c2412, 0x38086c24, 0x3090fc48, 0x21b1f890,
+        0x3f3f120,
+    ];
+    let mut chk: u32 = 1;
+    for value in customization.iter().map(|&b| b as u32).chain(words.iter().map(|&w| w as u32)) {
+        let b = chk >> 20;
+        chk = ((chk & 0xfffff) << 10) ^ value;
+        for (i, generator) in GEN.iter().enumerate() {
+            if (b >> i) & 1 == 1 {
+                chk ^= generator;
+            }
+        }
+    }
+    chk
+}
+
+fn rs1024_create_checksum(customization: &[u8], words: &[u16]) -> [u16; CHECKSUM_WORDS] {
+    let mut values = words.to_vec();
+    values.extend([0; CHECKSUM_WORDS]);
+    let polymod = rs1024_polymod(customization, &values) ^ 1;
+    [
+        ((polymod >> 20) & 0x3ff) as u16,
+        ((polymod >> 10) & 0x3ff) as u16,
+        (polymod & 0x3ff) as u16,
+    ]
+}
+
+/// 字节串左侧补零后按 10 位一组切成单词下标
+fn bytes_to_words(bytes: &[u8]) -> Zeroizing<Vec<u16>> {
+    let word_count = (bytes.len() * 8).div_ceil(RADIX_BITS);
+    let padding_bits = word_count * RADIX_BITS - bytes.len() * 8;
+    let mut words = Zeroizing::new(Vec::with_capacity(word_count));
+    let mut acc: u32 = 0;
+    let mut bits = padding_bits;
+    for &byte in bytes {
+        acc = (acc << 8) | byte as u32;
+        bits += 8;
+        while bits >= RADIX_BITS {
+            bits -= RADIX_BITS;
+            words.push(((acc >> bits) & 0x3ff) as u16);
+        }
+    }
+    words
+}
+
+/// `bytes_to_words` 的逆运算，补齐用的高位必须为零
+fn words_to_bytes(words: &[u16], padding_bits: usize) -> Option<Zeroizing<Vec<u8>>> {
+    let mut bytes = Zeroizing::new(Vec::with_capacity(words.len() * RADIX_BITS / 8));
+    let mut acc: u32 = 0;
+    let mut bits = 0usize;
+    let mut skip = padding_bits;
+    for &word in words {
+        acc = (acc << RADIX_BITS) | word as u32;
+        bits += RADIX_BITS;
+        if skip > 0 {
+            let taken = skip.min(bits);
+            if (acc >> (bits - taken)) != 0 {
+                return None;
+            }
+            bits -= taken;
+            skip -= taken;
+            acc &= (1 << bits) - 1;
+        }
+        while bits >= 8 {
+            bits -= 8;
+            bytes.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+        }
+    }
+    Some(bytes)
+}
+
+/// 四轮 Feistel 网络，轮函数为 PBKDF2-HMAC-SHA256
+fn feistel(
+    input: &[u8],
+    passphrase: &[u8],
+    iteration_exponent: u8,
+    id: u16,
+    extendable: bool,
+    encrypt: bool,
+) -> Zeroizing<Vec<u8>> {
+    let half = input.len() / 2;
+    let mut left = Zeroizing::new(input[..half].to_vec());
+    let mut right = Zeroizing::new(input[half..].to_vec());
+    let mut salt = Vec::new();
+    if !extendable {
+        salt.extend_from_slice(b"shamir");
+        salt.extend_from_slice(&id.to_be_bytes());
+    }
+    let iterations = (BASE_ITERATION_COUNT << iteration_exponent) / ROUND_COUNT as u32;
+
+    let rounds: Vec<u8> = if encrypt {
+        (0..ROUND_COUNT).collect()
+    } else {
+        (0..ROUND_COUNT).rev().collect()
+    };
+    for round in rounds {
+        let mut password = Zeroizing::new(vec![round]);
+        password.extend_from_slice(passphrase);
+        let mut round_salt = salt.clone();
+        round_salt.extend_from_slice(&right);
+        let mut f = Zeroizing::new(vec![0u8; half]);
+        pbkdf2_hmac::<Sha256>(&password, &round_salt, iterations, &mut f);
+        for (l, f) in left.iter_mut().zip(f.iter()) {
+            *l ^= f;
+        }
+        std::mem::swap(&mut left, &mut right);
+    }
+
+    let mut output = Zeroizing::new(right.to_vec());
+    output.extend_from_slice(&left);
+    output
+}
+
+fn digest(random_part: &[u8], secret: &[u8]) -> [u8; DIGEST_LEN] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(random_part).expect("HMAC accepts keys of any length");
+    mac.update(secret);
+    let mut digest = [0u8; DIGEST_LEN];
+    digest.copy_from_slice(&mac.finalize().into_bytes()[..DIGEST_LEN]);
+    digest
+}
+
+/// SLIP-39 的 Shamir 拆分：x 坐标从 0 开始，254 处存放摘要，255 处为秘密
+fn split_secret(threshold: u8, count: u8, secret: &[u8]) -> Vec<Point> {
+    if threshold == 1 {
+        return (0..count).map(|x| (x, Zeroizing::new(secret.to_vec()))).collect();
+    }
+    let mut rng = rand::thread_rng();
+    let mut shares: Vec<Point> = (0..threshold - 2)
+        .map(|x| {
+            let mut value = Zeroizing::new(vec![0u8; secret.len()]);
+            rng.fill_bytes(&mut value);
+            (x, value)
+        })
+        .collect();
+    let mut random_part = Zeroizing::new(vec![0u8; secret.len() - DIGEST_LEN]);
+    rng.fill_bytes(&mut random_part);
+    let mut digest_share = Zeroizing::new(digest(&random_part, secret).to_vec());
+    digest_share.extend_from_slice(&random_part);
+
+    let mut base: Vec<(u8, &[u8])> = shares.iter().map(|(x, value)| (*x, value.as_slice())).collect();
+    base.push((DIGEST_INDEX, &digest_share));
+    base.push((SECRET_INDEX, secret));
+    let derived: Vec<Point> = (threshold - 2..count).map(|x| (x, interpolate(&base, x))).collect();
+    shares.extend(derived);
+    shares
+}
+
+fn recover_secret(threshold: u8, shares: &[(u8, &[u8])]) -> Result<Zeroizing<Vec<u8>>, VaultError> {
+    if threshold == 1 {
+        return Ok(Zeroizing::new(shares[0].1.to_vec()));
+    }
+    let secret = interpolate(shares, SECRET_INDEX);
+    let digest_share = interpolate(shares, DIGEST_INDEX);
+    let (expected, random_part) = digest_share.split_at(DIGEST_LEN);
+    if digest(random_part, &secret) != expected {
+        return Err(VaultError::Share("shares do not reconstruct a valid secret".into()));
+    }
+    Ok(secret)
+}
+
+fn check_passphrase(passphrase: &[u8]) -> Result<(), VaultError> {
+    if passphrase.iter().all(|b| (32..=126).contains(b)) {
+        Ok(())
+    } else {
+        Err(VaultError::InvalidInput("SLIP-39 passphrase must only contain printable ASCII characters".into()))
+    }
+}
+
+/// 检查分组、门限、迭代指数和口令是否合法
+pub fn check_parameters(
+    group_threshold: u8,
+    groups: &[GroupSpec],
+    iteration_exponent: u8,
+    passphrase: &[u8],
+) -> Result<(), VaultError> {
+    let invalid = |msg: String| Err(VaultError::InvalidInput(msg));
+    if groups.is_empty() || groups.len() > MAX_SHARE_COUNT as usize {
+        return invalid(format!("Between 1 and {} groups are required", MAX_SHARE_COUNT));
+    }
+    if group_threshold == 0 || group_threshold as usize > groups.len() {
+        return invalid(format!(
+            "Group threshold must be between 1 and the number of groups ({})",
+            groups.len()
+        ));
+    }
+    for group in groups {
+        if group.count == 0 || group.count > MAX_SHARE_COUNT || group.threshold == 0 || group.threshold > group.count {
+            return invalid(format!(
+                "Invalid group {}of{}, member counts go up to {}",
+                group.threshold, group.count, MAX_SHARE_COUNT
+            ));
+        }
+        if group.threshold == 1 && group.count > 1 {
+            return invalid("Use 1of1 instead of several members with threshold 1".into());
+        }
+    }
+    if iteration_exponent > MAX_ITERATION_EXPONENT {
+        return invalid(format!("Iteration exponent must be at most {}", MAX_ITERATION_EXPONENT));
+    }
+    check_passphrase(passphrase)
+}
+
+/// 生成 SLIP-39 份额，返回每个组的助记词
+///
+/// 任意 `group_threshold` 个组、每组凑齐各自门限的成员即可恢复 `master_secret`。
+pub fn generate(
+    master_secret: &[u8],
+    passphrase: &[u8],
+    group_threshold: u8,
+    groups: &[GroupSpec],
+    extendable: bool,
+    iteration_exponent: u8,
+) -> Result<Vec<Vec<Zeroizing<String>>>, VaultError> {
+    let invalid = |msg: String| Err(VaultError::InvalidInput(msg));
+    if master_secret.len() < MIN_SECRET_LEN || !master_secret.len().is_multiple_of(2) {
+        return invalid(format!(
+            "Master secret must be an even number of bytes, at least {}",
+            MIN_SECRET_LEN
+        ));
+    }
+    check_parameters(group_threshold, groups, iteration_exponent, passphrase)?;
+
+    let id = (rand::thread_rng().next_u32() & ((1 << ID_BITS) - 1)) as u16;
+    let encrypted = feistel(master_secret, passphrase, iteration_exponent, id, extendable, true);
+    let group_shares = split_secret(group_threshold, groups.len() as u8, &encrypted);
+
+    Ok(group_shares
+        .iter()
+        .zip(groups)
+        .map(|((group_index, group_secret), group)| {
+            split_secret(group.threshold, group.count, group_secret)
+                .into_iter()
+                .map(|(member_index, value)| {
+                    Share {
+                        id,
+                        extendable,
+                        iteration_exponent,
+                        group_index: *group_index,
+                        group_threshold,
+                        group_count: groups.len() as u8,
+                        member_index,
+                        member_threshold: group.threshold,
+                        value,
+                    }
+                    .to_mnemonic()
+                })
+                .collect()
+        })
+        .collect())
+}
+
+/// 合并 SLIP-39 助记词恢复主密钥，拒绝来自不同秘密或参数不一致的份额
+pub fn combine<S: AsRef<str>>(mnemonics: &[S], passphrase: &[u8]) -> Result<Zeroizing<Vec<u8>>, VaultError> {
+    check_passphrase(passphrase)?;
+    let shares = mnemonics
+        .iter()
+        .map(|mnemonic| Share::from_mnemonic(mnemonic.as_ref()))
+        .collect::<Result<Vec<Share>, VaultError>>()?;
+    let first = shares.first().ok_or_else(|| VaultError::Share("no mnemonics given".into()))?;
+    for share in &shares {
+        if share.id != first.id
+            || share.extendable != first.extendable
+            || share.iteration_exponent != first.iteration_exponent
+        {
+            return Err(VaultError::Share("mnemonics do not belong to the same secret".into()));
+        }
+        if share.group_threshold != first.group_threshold
+            || share.group_count != first.group_count
+            || share.value.len() != first.value.len()
+        {
+            return Err(VaultError::Share("mnemonics have mismatching group parameters".into()));
+        }
+    }
+    if first.group_threshold > first.group_count {
+        return Err(VaultError::Share("group threshold exceeds the number of groups".into()));
+    }
+
+    let mut groups: BTreeMap<u8, Vec<&Share>> = BTreeMap::new();
+    for share in &shares {
+        let members = groups.entry(share.group_index).or_default();
+        if let Some(member) = members.first()
+            && member.member_threshold != share.member_threshold
+        {
+            return Err(VaultError::Share(format!(
+                "mnemonics of group {} have mismatching member thresholds",
+                share.group_index + 1
+            )));
+        }
+        match members.iter().find(|member| member.member_index == share.member_index) {
+            Some(member) if member.value != share.value => {
+                return Err(VaultError::Share(format!(
+                    "group {} has two different mnemonics with member index {}",
+                    share.group_index + 1,
+                    share.member_index + 1
+                )));
+            }
+            Some(_) => {}
+            None => members.push(share),
+        }
+    }
+
+    let complete: Vec<(u8, &Vec<&Share>)> = groups
+        .iter()
+        .filter(|(_, members)| members.len() >= members[0].member_threshold as usize)
+        .map(|(index, members)| (*index, members))
+        .collect();
+    if complete.len() < first.group_threshold as usize {
+        return Err(VaultError::Share(format!(
+            "{} of {} required groups are complete",
+            complete.len(),
+            first.group_threshold
+        )));
+    }
+
+    let mut group_secrets = Vec::with_capacity(first.group_threshold as usize);
+    for (group_index, members) in complete.iter().take(first.group_threshold as usize) {
+        let threshold = members[0].member_threshold;
+        let points: Vec<(u8, &[u8])> = members
+            .iter()
+            .take(threshold as usize)
+            .map(|member| (member.member_index, member.value.as_slice()))
+            .collect();
+        group_secrets.push((*group_index, recover_secret(threshold, &points)?));
+    }
+    let points: Vec<(u8, &[u8])> = group_secrets.iter().map(|(index, secret)| (*index, secret.as_slice())).collect();
+    let encrypted = recover_secret(first.group_threshold, &points)?;
+    Ok(feistel(&encrypted, passphrase, first.iteration_exponent, first.id, first.extendable, false))
+}
+
+/// 保险库中的 BIP-39 助记词对应的 SLIP-39 主密钥（即助记词的熵）及助记词的语言
+///
+/// 份额中不记录语言，同样的熵换一种语言就是另一个助记词、另一个钱包，恢复时必须使用原来的语言。
+pub fn master_secret_from_bip39(mnemonic: &SecretString) -> Result<(Zeroizing<Vec<u8>>, Language), VaultError> {
+    let mnemonic = crate::mnemonic::parse(mnemonic)?;
+    Ok((Zeroizing::new(mnemonic.to_entropy()), mnemonic.language()))
+}
+
+/// 把恢复出的主密钥还原为指定语言的 BIP-39 助记词
+pub fn bip39_from_master_secret(master_secret: &[u8], language: Language) -> Result<SecretString, VaultError> {
+    let mnemonic = bip39::Mnemonic::from_entropy_in(language, master_secret)
+        .map_err(|e| VaultError::Share(format!("recovered secret is not BIP-39 entropy: {}", e)))?;
+    Ok(SecretString::new(mnemonic.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unhex(text: &str) -> Vec<u8> {
+        (0..text.len()).step_by(2).map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap()).collect()
+    }
+
+    #[test]
+    fn official_test_vectors() {
+        let vectors: Vec<(String, Vec<String>, String)> =
+            serde_json::from_str(include_str!("../tests/slip39_vectors.json")).unwrap();
+        for (description, mnemonics, master_secret) in vectors {
+            let result = combine(&mnemonics, b"TREZOR");
+            if master_secret.is_empty() {
+                assert!(result.is_err(), "{}", description);
+            } else {
+                assert_eq!(result.unwrap().as_slice(), unhex(&master_secret), "{}", description);
+            }
+        }
+    }
+
+    #[test]
+    fn generated_shares_recover_the_secret() {
+        let secret = unhex("bb54aac4b89dc868ba37d9cc21b2cece");
+        let groups = [GroupSpec { threshold: 1, count: 1 }, GroupSpec { threshold: 2, count: 3 }, GroupSpec { threshold: 3, count: 5 }];
+        let shares = generate(&secret, b"TREZOR", 2, &groups, true, 0).unwrap();
+
+        let picked = [&shares[1][2], &shares[1][0], &shares[2][4], &shares[2][1], &shares[2][3]];
+        assert_eq!(combine(&picked, b"TREZOR").unwrap().as_slice(), secret);
+        assert_ne!(combine(&picked, b"").unwrap().as_slice(), secret);
+        assert!(combine(&picked[..4], b"TREZOR").is_err());
+    }
+
+    #[test]
+    fn non_english_mnemonic_round_trip() {
+        let entropy = unhex("bb54aac4b89dc868ba37d9cc21b2cece");
+        let original = bip39::Mnemonic::from_entropy_in(Language::SimplifiedChinese, &entropy).unwrap().to_string();
+        let (master_secret, language) = master_secret_from_bip39(&SecretString::from(original.as_str())).unwrap();
+        assert_eq!(language, Language::SimplifiedChinese);
+
+        let groups = [GroupSpec { threshold: 2, count: 3 }];
+        let shares = generate(&master_secret, b"", 1, &groups, true, 0).unwrap();
+        let recovered = combine(&[&shares[0][2], &shares[0][0]], b"").unwrap();
+        assert_eq!(bip39_from_master_secret(&recovered, language).unwrap().expose(), original);
+        assert_ne!(bip39_from_master_secret(&recovered, Language::English).unwrap().expose(), original);
+    }
+
+    #[test]
+    fn group_spec_parsing() {
+        let group: GroupSpec = "3of5".parse().unwrap();
+        assert_eq!((group.threshold, group.count), (3, 5));
+        let group: GroupSpec = "2-of-3".parse().unwrap();
+        assert_eq!((group.threshold, group.count), (2, 3));
+        assert!("3".parse::<GroupSpec>().is_err());
+    }
+}
@@ -1,63 +1,697 @@
 // vault_rpg/src/storage.rs
 
-use aes_gcm::{Aes256Gcm, Key, Nonce}; // AES-GCM encryption
-use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce}; // AES-GCM encryption
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm_siv::Aes256GcmSiv;
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
 use rand::RngCore;
 use pbkdf2::pbkdf2_hmac;
-use base64::{Engine as _, engine::general_purpose};
-use sha2::Sha256;
+use sha2::{Digest, Sha256};
+use std::time::{Duration, Instant};
+use zeroize::Zeroizing;
+
+use crate::contents::Contents;
+use crate::error::VaultError;
+use crate::format::{self, Cipher, FLAG_KEYFILE, Header, Kdf, KeySlot, MAX_KEY_SLOTS};
+use crate::secret::SecretString;
+
+pub const PBKDF2_ROUNDS: u32 = 100_000;
+pub const ARGON2_MEMORY_KIB: u32 = 64 * 1024;
+pub const ARGON2_ITERATIONS: u32 = 3;
+pub const ARGON2_PARALLELISM: u32 = 1;
+pub const SCRYPT_LOG_N: u8 = 17;
+pub const SCRYPT_R: u32 = 8;
+pub const SCRYPT_P: u32 = 1;
+
+/// 新建保险库时默认使用的加密算法
+pub const DEFAULT_CIPHER: Cipher = Cipher::XChaCha20Poly1305;
+
+/// 新建保险库时默认使用的 KDF
+pub const DEFAULT_KDF: Kdf = Kdf::Argon2id {
+    memory_kib: ARGON2_MEMORY_KIB,
+    iterations: ARGON2_ITERATIONS,
+    parallelism: ARGON2_PARALLELISM,
+};
+
+/// 新建保险库时允许的最弱参数，校准结果也不会低于这些值
+pub const PBKDF2_MIN_ROUNDS: u32 = 100_000;
+pub const ARGON2_MIN_MEMORY_KIB: u32 = 19 * 1024;
+pub const ARGON2_MIN_ITERATIONS: u32 = 2;
+pub const SCRYPT_MIN_LOG_N: u8 = 14;
+/// 校准 scrypt 时的起始 log_n
+const SCRYPT_CALIBRATION_LOG_N: u8 = 15;
+const PBKDF2_CALIBRATION_ROUNDS: u32 = 10_000;
 
-const PBKDF2_ROUNDS: u32 = 100_000;
 const SALT_LEN: usize = 16;
-const NONCE_LEN: usize = 12;
 const KEY_LEN: usize = 32;
 
-pub fn encrypt_mnemonic(mnemonic: &str, passphrase: &str) -> Result<String, String> {
-    let mut salt = [0u8; SALT_LEN];
-    rand::thread_rng().fill_bytes(&mut salt);
+/// 新建保险库时第一个密钥槽的标签
+pub const DEFAULT_SLOT_LABEL: &str = "default";
+
+/// 解锁凭据：主密码，以及可选的密钥文件摘要
+pub struct Credentials<'a> {
+    pub passphrase: &'a SecretString,
+    keyfile: Option<Zeroizing<[u8; 32]>>,
+}
+
+impl<'a> Credentials<'a> {
+    pub fn new(passphrase: &'a SecretString) -> Self {
+        Credentials { passphrase, keyfile: None }
+    }
+
+    /// 附加密钥文件，文件内容的 SHA-256 摘要会混入 KDF 输入
+    pub fn with_keyfile(mut self, path: &std::path::Path) -> Result<Self, VaultError> {
+        let contents = Zeroizing::new(std::fs::read(path)?);
+        if contents.is_empty() {
+            return Err(VaultError::InvalidInput(format!("Keyfile '{}' is empty", path.display())));
+        }
+        self.keyfile = Some(Zeroizing::new(Sha256::digest(contents.as_slice()).into()));
+        Ok(self)
+    }
+
+    pub fn has_keyfile(&self) -> bool {
+        self.keyfile.is_some()
+    }
+
+    /// KDF 的输入：`passphrase || sha256(keyfile)`
+    fn key_material(&self) -> Zeroizing<Vec<u8>> {
+        let mut material = Zeroizing::new(self.passphrase.expose().as_bytes().to_vec());
+        if let Some(digest) = &self.keyfile {
+            material.extend_from_slice(digest.as_slice());
+        }
+        material
+    }
+}
+
+fn derive_key(kdf: &Kdf, passphrase: &[u8], salt: &[u8]) -> Result<Zeroizing<[u8; KEY_LEN]>, VaultError> {
+    let mut key_bytes = Zeroizing::new([0u8; KEY_LEN]);
+    match kdf {
+        Kdf::Pbkdf2Sha256 { rounds } => {
+            pbkdf2_hmac::<Sha256>(passphrase, salt, *rounds, key_bytes.as_mut());
+        }
+        Kdf::Argon2id { memory_kib, iterations, parallelism } => {
+            let params = argon2::Params::new(*memory_kib, *iterations, *parallelism, Some(KEY_LEN))
+                .map_err(|e| VaultError::Kdf(format!("invalid Argon2id parameters: {}", e)))?;
+            argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params)
+                .hash_password_into(passphrase, salt, key_bytes.as_mut())
+                .map_err(|e| VaultError::Kdf(format!("Argon2id: {}", e)))?;
+        }
+        Kdf::Scrypt { log_n, r, p } => {
+            let params = scrypt::Params::new(*log_n, *r, *p, KEY_LEN)
+                .map_err(|e| VaultError::Kdf(format!("invalid scrypt parameters: {}", e)))?;
+            scrypt::scrypt(passphrase, salt, &params, key_bytes.as_mut())
+                .map_err(|e| VaultError::Kdf(format!("scrypt: {}", e)))?;
+        }
+    }
+    Ok(key_bytes)
+}
+
+fn seal(cipher: Cipher, key: &[u8; KEY_LEN], nonce: &[u8], payload: Payload) -> Result<Vec<u8>, VaultError> {
+    let result = match cipher {
+        Cipher::Aes256Gcm => Aes256Gcm::new(key.into()).encrypt(Nonce::from_slice(nonce), payload),
+        Cipher::XChaCha20Poly1305 => XChaCha20Poly1305::new(key.into()).encrypt(XNonce::from_slice(nonce), payload),
+        Cipher::Aes256GcmSiv => Aes256GcmSiv::new(key.into()).encrypt(Nonce::from_slice(nonce), payload),
+    };
+    result.map_err(|_| VaultError::Encryption)
+}
+
+fn open(cipher: Cipher, key: &[u8; KEY_LEN], nonce: &[u8], payload: Payload) -> Result<Zeroizing<Vec<u8>>, VaultError> {
+    let result = match cipher {
+        Cipher::Aes256Gcm => Aes256Gcm::new(key.into()).decrypt(Nonce::from_slice(nonce), payload),
+        Cipher::XChaCha20Poly1305 => XChaCha20Poly1305::new(key.into()).decrypt(XNonce::from_slice(nonce), payload),
+        Cipher::Aes256GcmSiv => Aes256GcmSiv::new(key.into()).decrypt(Nonce::from_slice(nonce), payload),
+    };
+    result.map(Zeroizing::new).map_err(|_| VaultError::Decryption)
+}
+
+fn random_bytes(len: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; len];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes
+}
 
-    let mut key_bytes = [0u8; KEY_LEN];
-    pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), &salt, PBKDF2_ROUNDS, &mut key_bytes);
-    let key = Key::<Aes256Gcm>::from_slice(&key_bytes);
+/// 用凭据派生的密钥包装数据密钥，生成一个新的密钥槽
+fn wrap_key(
+    data_key: &[u8; KEY_LEN],
+    credentials: &Credentials,
+    kdf: Kdf,
+    cipher: Cipher,
+    id: u8,
+    label: &str,
+) -> Result<KeySlot, VaultError> {
+    if label.len() > u8::MAX as usize {
+        return Err(VaultError::InvalidInput("Key slot label is too long".into()));
+    }
+    let mut slot = KeySlot {
+        id,
+        label: label.to_string(),
+        flags: if credentials.has_keyfile() { FLAG_KEYFILE } else { 0 },
+        kdf,
+        salt: random_bytes(SALT_LEN),
+        cipher,
+        nonce: random_bytes(cipher.nonce_len()),
+        wrapped_key: Vec::new(),
+    };
+    let wrapping_key = derive_key(&kdf, &credentials.key_material(), &slot.salt)?;
+    let aad = slot.associated_data();
+    slot.wrapped_key = seal(cipher, &wrapping_key, &slot.nonce, Payload { msg: data_key, aad: &aad })?;
+    Ok(slot)
+}
 
-    let cipher = Aes256Gcm::new(key);
+/// 解开密钥槽，得到正文的加密密钥
+///
+/// 旧格式的密钥槽没有包装的数据密钥，派生出的密钥直接用于正文。
+fn unwrap_key(slot: &KeySlot, credentials: &Credentials) -> Result<Zeroizing<[u8; KEY_LEN]>, VaultError> {
+    let wrapping_key = derive_key(&slot.kdf, &credentials.key_material(), &slot.salt)?;
+    if slot.wrapped_key.is_empty() {
+        return Ok(wrapping_key);
+    }
+    let aad = slot.associated_data();
+    let data_key = open(slot.cipher, &wrapping_key, &slot.nonce, Payload { msg: &slot.wrapped_key, aad: &aad })?;
+    let data_key: [u8; KEY_LEN] = data_key
+        .as_slice()
+        .try_into()
+        .map_err(|_| VaultError::Format("wrapped key has an invalid length".into()))?;
+    Ok(Zeroizing::new(data_key))
+}
 
-    let mut nonce_bytes = [0u8; NONCE_LEN];
-    rand::thread_rng().fill_bytes(&mut nonce_bytes);
-    let nonce = Nonce::from_slice(&nonce_bytes);
+/// 校验保险库名称，依次尝试与凭据匹配的密钥槽，返回解开的槽位下标和正文密钥
+fn unlock(
+    header: &Header,
+    credentials: &Credentials,
+    vault_name: &str,
+) -> Result<(usize, Zeroizing<[u8; KEY_LEN]>), VaultError> {
+    if let Some(recorded) = &header.vault_name
+        && recorded != vault_name
+    {
+        return Err(VaultError::IdentityMismatch {
+            expected: vault_name.to_string(),
+            found: recorded.clone(),
+        });
+    }
 
-    match cipher.encrypt(nonce, mnemonic.as_bytes()) {
-        Ok(ciphertext) => {
-            let mut output = Vec::new();
-            output.extend(&salt);
-            output.extend(&nonce_bytes);
-            output.extend(ciphertext);
-            Ok(general_purpose::STANDARD.encode(&output))
+    let mut candidates = header
+        .slots
+        .iter()
+        .enumerate()
+        .filter(|(_, slot)| slot.requires_keyfile() == credentials.has_keyfile())
+        .peekable();
+    if candidates.peek().is_none() {
+        return Err(if credentials.has_keyfile() {
+            VaultError::InvalidInput(format!("Vault '{}' does not use a keyfile", vault_name))
+        } else {
+            VaultError::KeyfileRequired(vault_name.to_string())
+        });
+    }
+    for (index, slot) in candidates {
+        match unwrap_key(slot, credentials) {
+            Ok(key) => return Ok((index, key)),
+            Err(VaultError::Decryption) => continue,
+            Err(e) => return Err(e),
         }
-        Err(e) => Err(format!("Encryption failed: {:?}", e)),
     }
+    Err(VaultError::Decryption)
+}
+
+fn open_payload(header: &Header, key: &[u8; KEY_LEN], ciphertext: &[u8]) -> Result<Contents, VaultError> {
+    let aad = header.associated_data();
+    let plaintext = open(header.cipher, key, &header.nonce, Payload { msg: ciphertext, aad: &aad })?;
+    Contents::from_bytes(&plaintext)
+}
+
+fn seal_payload(header: &Header, key: &[u8; KEY_LEN], contents: &Contents) -> Result<Vec<u8>, VaultError> {
+    seal_bytes(header, key, &contents.to_bytes())
+}
+
+fn seal_bytes(header: &Header, key: &[u8; KEY_LEN], plaintext: &[u8]) -> Result<Vec<u8>, VaultError> {
+    let aad = header.associated_data();
+    let ciphertext = seal(header.cipher, key, &header.nonce, Payload { msg: plaintext, aad: &aad })?;
+    let mut output = header.to_bytes();
+    output.extend(ciphertext);
+    Ok(output)
+}
+
+fn with_ciphertext(header: &Header, ciphertext: &[u8]) -> Vec<u8> {
+    let mut output = header.to_bytes();
+    output.extend(ciphertext);
+    output
+}
+
+/// 加密保险库内容：随机生成数据密钥加密正文，再用凭据派生的密钥包装到第一个密钥槽
+///
+/// 保险库名称作为关联数据绑定到正文密文上。
+pub fn encrypt_contents(
+    contents: &Contents,
+    credentials: &Credentials,
+    kdf: Kdf,
+    cipher: Cipher,
+    vault_name: &str,
+) -> Result<Vec<u8>, VaultError> {
+    encrypt_bytes(&contents.to_bytes(), credentials, kdf, cipher, vault_name)
+}
+
+/// 解密保险库内容，文件头记录的名称与 `vault_name` 不一致时拒绝解密
+pub fn decrypt_contents(encoded: &[u8], credentials: &Credentials, vault_name: &str) -> Result<Contents, VaultError> {
+    let (header, ciphertext) = format::parse(encoded)?;
+    let (_, key) = unlock(&header, credentials, vault_name)?;
+    open_payload(&header, &key, &ciphertext)
+}
+
+/// 用与保险库文件相同的格式加密任意数据（如导出包），`name` 写入文件头并参与认证
+pub fn encrypt_bytes(
+    plaintext: &[u8],
+    credentials: &Credentials,
+    kdf: Kdf,
+    cipher: Cipher,
+    name: &str,
+) -> Result<Vec<u8>, VaultError> {
+    let mut data_key = Zeroizing::new([0u8; KEY_LEN]);
+    rand::thread_rng().fill_bytes(data_key.as_mut());
+
+    let slot = wrap_key(&data_key, credentials, kdf, cipher, 0, DEFAULT_SLOT_LABEL)?;
+    let header = Header::new(cipher, random_bytes(cipher.nonce_len()), name, vec![slot]);
+    seal_bytes(&header, &data_key, plaintext)
+}
+
+/// 解密 `encrypt_bytes` 生成的数据，文件头中的名称必须为 `name`
+pub fn decrypt_bytes(encoded: &[u8], credentials: &Credentials, name: &str) -> Result<Zeroizing<Vec<u8>>, VaultError> {
+    let (header, ciphertext) = format::parse(encoded)?;
+    if header.is_legacy() {
+        return Err(VaultError::Format("unexpected legacy header".into()));
+    }
+    let (_, key) = unlock(&header, credentials, name)?;
+    let aad = header.associated_data();
+    open(header.cipher, &key, &header.nonce, Payload { msg: &ciphertext, aad: &aad })
+}
+
+/// 用新的 nonce 重新加密修改后的内容，密钥槽保持不变。返回新文件内容和 `update` 的结果
+pub fn update_contents<T>(
+    encoded: &[u8],
+    vault_name: &str,
+    credentials: &Credentials,
+    update: impl FnOnce(&mut Contents) -> Result<T, VaultError>,
+) -> Result<(Vec<u8>, T), VaultError> {
+    let (mut header, ciphertext) = upgrade(encoded, credentials, vault_name)?;
+    let (_, data_key) = unlock(&header, credentials, vault_name)?;
+    let mut contents = open_payload(&header, &data_key, &ciphertext)?;
+    let result = update(&mut contents)?;
+    header.nonce = random_bytes(header.cipher.nonce_len());
+    Ok((seal_payload(&header, &data_key, &contents)?, result))
 }
 
-pub fn decrypt_mnemonic(encoded: &[u8], passphrase: &str) -> Result<String, String> {
-    let data = general_purpose::STANDARD.decode(encoded).map_err(|e| format!("base64 decode failed: {:?}", e))?;
-    if data.len() < SALT_LEN + NONCE_LEN {
-        return Err("Invalid data length".into());
+/// 把保险库绑定到新名称：用新的 nonce 和名称重新加密正文，密钥槽保持不变，用于重命名和复制
+pub fn rebind(encoded: &[u8], vault_name: &str, new_name: &str, credentials: &Credentials) -> Result<Vec<u8>, VaultError> {
+    let (mut header, ciphertext) = upgrade(encoded, credentials, vault_name)?;
+    let (_, data_key) = unlock(&header, credentials, vault_name)?;
+    let contents = open_payload(&header, &data_key, &ciphertext)?;
+    header.vault_name = Some(new_name.to_string());
+    header.nonce = random_bytes(header.cipher.nonce_len());
+    seal_payload(&header, &data_key, &contents)
+}
+
+/// 旧格式的文件先用原来的凭据和参数转换为当前格式
+fn upgrade(encoded: &[u8], credentials: &Credentials, vault_name: &str) -> Result<(Header, Vec<u8>), VaultError> {
+    let (header, ciphertext) = format::parse(encoded)?;
+    if !header.is_legacy() {
+        return Ok((header, ciphertext));
     }
+    let contents = decrypt_contents(encoded, credentials, vault_name)?;
+    let upgraded = encrypt_contents(&contents, credentials, header.slots[0].kdf, header.cipher, vault_name)?;
+    format::parse(&upgraded)
+}
 
-    let salt = &data[..SALT_LEN];
-    let nonce_bytes = &data[SALT_LEN..SALT_LEN + NONCE_LEN];
-    let ciphertext = &data[SALT_LEN + NONCE_LEN..];
+/// 更换解开的那个密钥槽的凭据（新的盐和 nonce），可同时更换 KDF 和正文加密算法
+///
+/// 其他密钥槽保持不变；旧格式的文件会整体重新加密为当前格式。返回新文件内容以及使用的 KDF 和加密算法。
+pub fn rekey(
+    encoded: &[u8],
+    vault_name: &str,
+    old: &Credentials,
+    new: &Credentials,
+    choose_kdf: impl FnOnce(Kdf) -> Result<Kdf, VaultError>,
+    cipher: Option<Cipher>,
+) -> Result<(Vec<u8>, Kdf, Cipher), VaultError> {
+    let (header, ciphertext) = format::parse(encoded)?;
+    if header.is_legacy() {
+        let contents = decrypt_contents(encoded, old, vault_name)?;
+        let kdf = choose_kdf(header.slots[0].kdf)?;
+        let cipher = cipher.unwrap_or(header.cipher);
+        return Ok((encrypt_contents(&contents, new, kdf, cipher, vault_name)?, kdf, cipher));
+    }
 
-    let mut key_bytes = [0u8; KEY_LEN];
-    pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, PBKDF2_ROUNDS, &mut key_bytes);
-    let key =  Key::<Aes256Gcm>::from_slice(&key_bytes);
-    let nonce = Nonce::from_slice(nonce_bytes);
-    let cipher = Aes256Gcm::new(key);
+    let (index, data_key) = unlock(&header, old, vault_name)?;
+    let current = &header.slots[index];
+    let kdf = choose_kdf(current.kdf)?;
+    let slot_cipher = cipher.unwrap_or(current.cipher);
+    let slot = wrap_key(&data_key, new, kdf, slot_cipher, current.id, &current.label.clone())?;
 
-    match cipher.decrypt(nonce, ciphertext) {
-        Ok(plaintext) => Ok(String::from_utf8_lossy(&plaintext).to_string()),
-        Err(e) => Err(format!("Decryption failed: {:?}", e)),
+    let mut header = header;
+    header.slots[index] = slot;
+    match cipher {
+        Some(cipher) if cipher != header.cipher => {
+            let contents = open_payload(&header, &data_key, &ciphertext)?;
+            header.cipher = cipher;
+            header.nonce = random_bytes(cipher.nonce_len());
+            Ok((seal_payload(&header, &data_key, &contents)?, kdf, cipher))
+        }
+        _ => Ok((with_ciphertext(&header, &ciphertext), kdf, header.cipher)),
     }
 }
 
+/// 添加一个新的密钥槽，正文不需要重新加密。返回新文件内容和新槽位编号
+pub fn add_key_slot(
+    encoded: &[u8],
+    vault_name: &str,
+    credentials: &Credentials,
+    new: &Credentials,
+    label: &str,
+    kdf: Kdf,
+) -> Result<(Vec<u8>, u8), VaultError> {
+    let (mut header, ciphertext) = upgrade(encoded, credentials, vault_name)?;
+    let (_, data_key) = unlock(&header, credentials, vault_name)?;
+    let id = (0..MAX_KEY_SLOTS as u8)
+        .find(|id| header.slots.iter().all(|slot| slot.id != *id))
+        .ok_or_else(|| VaultError::InvalidInput(format!("Vault '{}' already has {} key slots", vault_name, MAX_KEY_SLOTS)))?;
+
+    let slot = wrap_key(&data_key, new, kdf, header.cipher, id, label)?;
+    header.slots.push(slot);
+    Ok((with_ciphertext(&header, &ciphertext), id))
+}
 
+/// 撤销一个密钥槽，需要任一有效凭据，不能撤销最后一个
+pub fn revoke_key_slot(encoded: &[u8], vault_name: &str, credentials: &Credentials, id: u8) -> Result<Vec<u8>, VaultError> {
+    let (mut header, ciphertext) = format::parse(encoded)?;
+    if header.is_legacy() {
+        return Err(VaultError::InvalidInput(format!(
+            "Vault '{}' uses a legacy format with a single key, run rekey to upgrade it",
+            vault_name
+        )));
+    }
+    unlock(&header, credentials, vault_name)?;
+
+    let index = header
+        .slots
+        .iter()
+        .position(|slot| slot.id == id)
+        .ok_or_else(|| VaultError::InvalidInput(format!("Key slot {} does not exist", id)))?;
+    if header.slots.len() == 1 {
+        return Err(VaultError::InvalidInput("Cannot revoke the last key slot".into()));
+    }
+    header.slots.remove(index);
+    Ok(with_ciphertext(&header, &ciphertext))
+}
+
+/// 读取保险库文件头（不需要口令）
+pub fn read_header(encoded: &[u8]) -> Result<Header, VaultError> {
+    Ok(format::parse(encoded)?.0)
+}
+
+/// 拒绝强度低于下限、可以被快速暴力破解的 KDF 参数
+pub fn check_strength(kdf: &Kdf) -> Result<(), VaultError> {
+    let weak = match *kdf {
+        Kdf::Pbkdf2Sha256 { rounds } if rounds < PBKDF2_MIN_ROUNDS => {
+            format!("pbkdf2 needs at least {} rounds", PBKDF2_MIN_ROUNDS)
+        }
+        Kdf::Argon2id { memory_kib, .. } if memory_kib < ARGON2_MIN_MEMORY_KIB => {
+            format!("argon2id needs at least {} KiB of memory", ARGON2_MIN_MEMORY_KIB)
+        }
+        Kdf::Argon2id { iterations, .. } if iterations < ARGON2_MIN_ITERATIONS => {
+            format!("argon2id needs at least {} iterations", ARGON2_MIN_ITERATIONS)
+        }
+        Kdf::Scrypt { log_n, .. } if log_n < SCRYPT_MIN_LOG_N => {
+            format!("scrypt needs at least {} KiB of memory", 1u32 << SCRYPT_MIN_LOG_N)
+        }
+        _ => return Ok(()),
+    };
+    Err(VaultError::InvalidInput(format!("KDF parameters are too weak: {}", weak)))
+}
+
+fn time_kdf(kdf: &Kdf) -> Result<Duration, VaultError> {
+    let start = Instant::now();
+    derive_key(kdf, b"vault_rpg calibration", &[0u8; SALT_LEN])?;
+    Ok(start.elapsed())
+}
+
+/// 在当前机器上测量与 `kdf` 同类的 KDF，返回接近目标解锁时间的参数及实测耗时
+pub fn calibrate_kdf(kdf: &Kdf, target: Duration) -> Result<(Kdf, Duration), VaultError> {
+    let calibrated = match kdf {
+        Kdf::Argon2id { parallelism, .. } => {
+            // 先在单轮下把内存加到目标时间允许的最大值，再用剩余时间增加轮数
+            let mut memory_kib = ARGON2_MEMORY_KIB;
+            let single = |memory_kib| Kdf::Argon2id { memory_kib, iterations: 1, parallelism: *parallelism };
+            let mut elapsed = time_kdf(&single(memory_kib))?;
+            while elapsed > target && memory_kib / 2 >= ARGON2_MIN_MEMORY_KIB {
+                memory_kib /= 2;
+                elapsed = time_kdf(&single(memory_kib))?;
+            }
+            while elapsed * 2 <= target && memory_kib * 2 <= format::ARGON2_MAX_MEMORY_KIB {
+                memory_kib *= 2;
+                elapsed = time_kdf(&single(memory_kib))?;
+            }
+            let iterations = ((target.as_secs_f64() / elapsed.as_secs_f64()).floor() as u32)
+                .clamp(ARGON2_MIN_ITERATIONS, format::ARGON2_MAX_ITERATIONS);
+            Kdf::Argon2id { memory_kib, iterations, parallelism: *parallelism }
+        }
+        Kdf::Scrypt { r, p, .. } => {
+            let mut log_n = SCRYPT_CALIBRATION_LOG_N;
+            let mut elapsed = time_kdf(&Kdf::Scrypt { log_n, r: *r, p: *p })?;
+            while elapsed * 2 <= target && log_n < format::SCRYPT_MAX_LOG_N {
+                log_n += 1;
+                elapsed = time_kdf(&Kdf::Scrypt { log_n, r: *r, p: *p })?;
+            }
+            Kdf::Scrypt { log_n, r: *r, p: *p }
+        }
+        Kdf::Pbkdf2Sha256 { .. } => {
+            let elapsed = time_kdf(&Kdf::Pbkdf2Sha256 { rounds: PBKDF2_CALIBRATION_ROUNDS })?;
+            let scale = target.as_secs_f64() / elapsed.as_secs_f64();
+            let rounds = (PBKDF2_CALIBRATION_ROUNDS as f64 * scale) as u32;
+            Kdf::Pbkdf2Sha256 { rounds: rounds.clamp(PBKDF2_MIN_ROUNDS, format::PBKDF2_MAX_ROUNDS) }
+        }
+    };
+    let elapsed = time_kdf(&calibrated)?;
+    Ok((calibrated, elapsed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::tests::legacy_header;
+    use base64::{Engine as _, engine::general_purpose};
+
+    /// 测试用的弱参数，只为了让测试跑得快
+    const FAST_KDFS: [Kdf; 3] = [
+        Kdf::Pbkdf2Sha256 { rounds: 1 },
+        Kdf::Argon2id { memory_kib: 8, iterations: 1, parallelism: 1 },
+        Kdf::Scrypt { log_n: 4, r: 8, p: 1 },
+    ];
+    const CIPHERS: [Cipher; 3] = [Cipher::Aes256Gcm, Cipher::XChaCha20Poly1305, Cipher::Aes256GcmSiv];
+    const MNEMONIC: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    fn contents() -> Contents {
+        Contents::new(SecretString::from(MNEMONIC), None)
+    }
+
+    fn mnemonic_of(contents: &Contents) -> String {
+        contents.mnemonic(None).unwrap().secret.expose().to_string()
+    }
+
+    /// 按 v0-v3 的方式加密：口令派生的密钥直接加密正文，v2 起文件头作为关联数据
+    fn legacy_file(version: u8, passphrase: &str, name: &str) -> Vec<u8> {
+        let (salt, nonce) = ([1u8; 16], [2u8; 12]);
+        let rounds = if version == 0 { 100_000 } else { 1 };
+        let key = derive_key(&Kdf::Pbkdf2Sha256 { rounds }, passphrase.as_bytes(), &salt).unwrap();
+        if version == 0 {
+            let ciphertext = seal(Cipher::Aes256Gcm, &key, &nonce, Payload { msg: MNEMONIC.as_bytes(), aad: &[] }).unwrap();
+            let raw = [salt.as_slice(), &nonce, &ciphertext].concat();
+            return general_purpose::STANDARD.encode(raw).into_bytes();
+        }
+        let mut data = legacy_header(version, Kdf::Pbkdf2Sha256 { rounds }, &salt, &nonce, name, 0);
+        let aad = if version >= 2 { data.clone() } else { Vec::new() };
+        data.extend(seal(Cipher::Aes256Gcm, &key, &nonce, Payload { msg: MNEMONIC.as_bytes(), aad: &aad }).unwrap());
+        data
+    }
+
+    #[test]
+    fn round_trip_with_every_kdf_and_cipher() {
+        let passphrase = SecretString::from("correct horse");
+        let credentials = Credentials::new(&passphrase);
+        for kdf in FAST_KDFS {
+            for cipher in CIPHERS {
+                let encoded = encrypt_contents(&contents(), &credentials, kdf, cipher, "wallet").unwrap();
+                let header = read_header(&encoded).unwrap();
+                assert_eq!(header.cipher, cipher);
+                assert_eq!(header.slots[0].kdf, kdf);
+                let decrypted = decrypt_contents(&encoded, &credentials, "wallet").unwrap();
+                assert_eq!(mnemonic_of(&decrypted), MNEMONIC, "{} / {}", kdf, cipher.name());
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_wrong_credentials_and_tampering() {
+        let passphrase = SecretString::from("correct horse");
+        let credentials = Credentials::new(&passphrase);
+        let encoded = encrypt_contents(&contents(), &credentials, FAST_KDFS[0], Cipher::XChaCha20Poly1305, "wallet").unwrap();
+
+        let wrong = SecretString::from("wrong horse");
+        assert!(matches!(decrypt_contents(&encoded, &Credentials::new(&wrong), "wallet"), Err(VaultError::Decryption)));
+        // 把文件改名为别的保险库不能绕过名称检查
+        assert!(matches!(
+            decrypt_contents(&encoded, &credentials, "other"),
+            Err(VaultError::IdentityMismatch { .. })
+        ));
+
+        // 改动文件头中的名称：名称检查与关联数据都能发现
+        let position = encoded.windows(6).position(|window| window == b"wallet").unwrap();
+        let mut renamed = encoded.clone();
+        renamed[position..position + 6].copy_from_slice(b"wallex");
+        assert!(matches!(decrypt_contents(&renamed, &credentials, "wallex"), Err(VaultError::Decryption)));
+
+        let mut flipped = encoded.clone();
+        *flipped.last_mut().unwrap() ^= 1;
+        assert!(matches!(decrypt_contents(&flipped, &credentials, "wallet"), Err(VaultError::Decryption)));
+    }
+
+    #[test]
+    fn decrypts_legacy_formats() {
+        let passphrase = SecretString::from("legacy");
+        let credentials = Credentials::new(&passphrase);
+        for version in 0..=3 {
+            let encoded = legacy_file(version, "legacy", "wallet");
+            let decrypted = decrypt_contents(&encoded, &credentials, "wallet").unwrap();
+            assert_eq!(mnemonic_of(&decrypted), MNEMONIC, "v{}", version);
+
+            let wrong = SecretString::from("wrong");
+            assert!(decrypt_contents(&encoded, &Credentials::new(&wrong), "wallet").is_err(), "v{}", version);
+        }
+        // v2 起名称参与认证
+        for version in 2..=3 {
+            let encoded = legacy_file(version, "legacy", "wallet");
+            assert!(matches!(
+                decrypt_contents(&encoded, &credentials, "other"),
+                Err(VaultError::IdentityMismatch { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn legacy_files_upgrade_to_the_current_format() {
+        let passphrase = SecretString::from("legacy");
+        let credentials = Credentials::new(&passphrase);
+        let (upgraded, ()) = update_contents(&legacy_file(3, "legacy", "wallet"), "wallet", &credentials, |_| Ok(())).unwrap();
+        let header = read_header(&upgraded).unwrap();
+        assert_eq!(header.version, format::FORMAT_VERSION);
+        assert_eq!(header.slots[0].kdf, Kdf::Pbkdf2Sha256 { rounds: 1 });
+        assert_eq!(mnemonic_of(&decrypt_contents(&upgraded, &credentials, "wallet").unwrap()), MNEMONIC);
+
+        let renamed = rebind(&upgraded, "wallet", "savings", &credentials).unwrap();
+        assert_eq!(read_header(&renamed).unwrap().vault_name.as_deref(), Some("savings"));
+        assert!(decrypt_contents(&renamed, &credentials, "wallet").is_err());
+        assert_eq!(mnemonic_of(&decrypt_contents(&renamed, &credentials, "savings").unwrap()), MNEMONIC);
+    }
+
+    #[test]
+    fn encrypted_bytes_are_bound_to_their_name() {
+        let passphrase = SecretString::from("export");
+        let credentials = Credentials::new(&passphrase);
+        let encoded = encrypt_bytes(b"payload", &credentials, FAST_KDFS[0], Cipher::Aes256GcmSiv, "bundle").unwrap();
+        assert_eq!(decrypt_bytes(&encoded, &credentials, "bundle").unwrap().as_slice(), b"payload");
+        assert!(decrypt_bytes(&encoded, &credentials, "vault").is_err());
+        assert!(decrypt_bytes(&legacy_file(3, "export", "bundle"), &credentials, "bundle").is_err());
+    }
+
+    #[test]
+    fn every_key_slot_unlocks_until_revoked() {
+        let passphrases: Vec<SecretString> = (0..3).map(|i| SecretString::from(format!("passphrase {}", i))).collect();
+        let first = Credentials::new(&passphrases[0]);
+        let mut encoded = encrypt_contents(&contents(), &first, FAST_KDFS[0], Cipher::XChaCha20Poly1305, "wallet").unwrap();
+        for (passphrase, kdf) in passphrases[1..].iter().zip(&FAST_KDFS[1..]) {
+            let (added, _) = add_key_slot(&encoded, "wallet", &first, &Credentials::new(passphrase), "extra", *kdf).unwrap();
+            encoded = added;
+        }
+        let ids: Vec<u8> = read_header(&encoded).unwrap().slots.iter().map(|slot| slot.id).collect();
+        assert_eq!(ids, vec![0, 1, 2]);
+        for passphrase in &passphrases {
+            let decrypted = decrypt_contents(&encoded, &Credentials::new(passphrase), "wallet").unwrap();
+            assert_eq!(mnemonic_of(&decrypted), MNEMONIC);
+        }
+
+        // 任一有效凭据都可以撤销其他槽，被撤销的凭据随即失效，其余不受影响
+        let revoked = revoke_key_slot(&encoded, "wallet", &Credentials::new(&passphrases[2]), 1).unwrap();
+        assert!(matches!(
+            decrypt_contents(&revoked, &Credentials::new(&passphrases[1]), "wallet"),
+            Err(VaultError::Decryption)
+        ));
+        for passphrase in [&passphrases[0], &passphrases[2]] {
+            assert!(decrypt_contents(&revoked, &Credentials::new(passphrase), "wallet").is_ok());
+        }
+        let wrong = SecretString::from("wrong");
+        assert!(revoke_key_slot(&revoked, "wallet", &Credentials::new(&wrong), 0).is_err());
+        assert!(revoke_key_slot(&revoked, "wallet", &first, 1).is_err());
+
+        // 不能撤销最后一个槽
+        let single = revoke_key_slot(&revoked, "wallet", &first, 2).unwrap();
+        assert!(revoke_key_slot(&single, "wallet", &first, 0).is_err());
+        // 空出来的编号会被复用
+        let (_, id) = add_key_slot(&revoked, "wallet", &first, &Credentials::new(&passphrases[1]), "again", FAST_KDFS[0]).unwrap();
+        assert_eq!(id, 1);
+    }
+
+    #[test]
+    fn key_slots_are_limited() {
+        let passphrase = SecretString::from("passphrase");
+        let credentials = Credentials::new(&passphrase);
+        let mut encoded = encrypt_contents(&contents(), &credentials, FAST_KDFS[0], Cipher::Aes256Gcm, "wallet").unwrap();
+        for _ in 1..MAX_KEY_SLOTS {
+            encoded = add_key_slot(&encoded, "wallet", &credentials, &credentials, "extra", FAST_KDFS[0]).unwrap().0;
+        }
+        assert_eq!(read_header(&encoded).unwrap().slots.len(), MAX_KEY_SLOTS);
+        assert!(matches!(
+            add_key_slot(&encoded, "wallet", &credentials, &credentials, "extra", FAST_KDFS[0]),
+            Err(VaultError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn keyfile_slots_need_the_keyfile() {
+        let keyfile = std::env::temp_dir().join(format!("vault_rpg_test_keyfile_{}", std::process::id()));
+        std::fs::write(&keyfile, b"keyfile contents").unwrap();
+        let passphrase = SecretString::from("passphrase");
+        let plain = Credentials::new(&passphrase);
+        let with_keyfile = Credentials::new(&passphrase).with_keyfile(&keyfile).unwrap();
+        std::fs::remove_file(&keyfile).unwrap();
+
+        let encoded = encrypt_contents(&contents(), &with_keyfile, FAST_KDFS[0], Cipher::Aes256Gcm, "wallet").unwrap();
+        assert!(read_header(&encoded).unwrap().requires_keyfile());
+        assert!(matches!(decrypt_contents(&encoded, &plain, "wallet"), Err(VaultError::KeyfileRequired(_))));
+        assert!(decrypt_contents(&encoded, &with_keyfile, "wallet").is_ok());
+
+        // 再加一个只用口令的槽后，两种凭据都能解锁
+        let (encoded, _) = add_key_slot(&encoded, "wallet", &with_keyfile, &plain, "no keyfile", FAST_KDFS[0]).unwrap();
+        assert!(!read_header(&encoded).unwrap().requires_keyfile());
+        assert!(decrypt_contents(&encoded, &plain, "wallet").is_ok());
+        assert!(decrypt_contents(&encoded, &with_keyfile, "wallet").is_ok());
+    }
+
+    #[test]
+    fn rekey_replaces_only_the_unlocked_slot() {
+        let (old, other, new) = (SecretString::from("old"), SecretString::from("other"), SecretString::from("new"));
+        let encoded = encrypt_contents(&contents(), &Credentials::new(&old), FAST_KDFS[0], Cipher::Aes256Gcm, "wallet").unwrap();
+        let (encoded, _) =
+            add_key_slot(&encoded, "wallet", &Credentials::new(&old), &Credentials::new(&other), "other", FAST_KDFS[0]).unwrap();
+        let (rekeyed, kdf, cipher) = rekey(
+            &encoded,
+            "wallet",
+            &Credentials::new(&old),
+            &Credentials::new(&new),
+            |_| Ok(FAST_KDFS[2]),
+            Some(Cipher::XChaCha20Poly1305),
+        )
+        .unwrap();
+        assert_eq!((kdf, cipher), (FAST_KDFS[2], Cipher::XChaCha20Poly1305));
+        assert!(decrypt_contents(&rekeyed, &Credentials::new(&old), "wallet").is_err());
+        for passphrase in [&other, &new] {
+            assert_eq!(mnemonic_of(&decrypt_contents(&rekeyed, &Credentials::new(passphrase), "wallet").unwrap()), MNEMONIC);
+        }
+    }
+}
@@ -1,12 +1,16 @@
 use totp_rs::{Algorithm, TOTP, Secret};
 use base64::{Engine as _, engine::general_purpose};
+use zeroize::Zeroizing;
+
+use crate::error::VaultError;
+use crate::secret::SecretString;
 
 pub struct TotpConfig {
     pub algorithm: Algorithm,
     pub digits: u8,
     pub skew: u8,
     pub step: u64,
-    pub secret: String,
+    pub secret: SecretString,
     pub issuer: Option<String>,
     pub account: String,
 }
@@ -18,25 +22,36 @@ impl Default for TotpConfig {
             digits: 6,
             skew: 1,
             step: 30,
-            secret: String::new(),
+            secret: SecretString::default(),
             issuer: None,
             account: String::new(),
         }
     }
 }
 
-pub fn generate_totp_secret(config: &TotpConfig) -> Result<(), Box<dyn std::error::Error>> {
-    let secret_bytes = Secret::Encoded(config.secret.clone()).to_bytes()?;
-    let totp = TOTP::new(
+/// 密钥的每一份副本都会被清零：`Secret` 和 `TOTP` 在 totp-rs 的 zeroize 特性下释放时清零，
+/// 解码出的字节在交给 `TOTP` 之前放在 `Zeroizing` 中
+fn build_totp(config: &TotpConfig) -> Result<TOTP, VaultError> {
+    let encoded = Secret::Encoded(config.secret.expose().to_string());
+    let mut secret_bytes = Zeroizing::new(
+        encoded.to_bytes().map_err(|e| VaultError::Totp(format!("invalid secret: {:?}", e)))?,
+    );
+    drop(encoded);
+    TOTP::new(
         config.algorithm,
         config.digits.into(),
         config.skew,
         config.step,
-        secret_bytes,
+        std::mem::take(&mut *secret_bytes),
         config.issuer.clone(),
         config.account.clone(),
-    )?;
-    
+    )
+    .map_err(|e| VaultError::Totp(e.to_string()))
+}
+
+pub fn generate_totp_secret(config: &TotpConfig) -> Result<(), VaultError> {
+    let totp = build_totp(config)?;
+
     match totp.get_qr_base64() {
         Ok(base64) => {
             let png_bytes = general_purpose::STANDARD.decode(base64)?;
@@ -47,36 +62,34 @@ pub fn generate_totp_secret(config: &TotpConfig) -> Result<(), Box<dyn std::erro
         },
         Err(e) => {
             eprintln!("❌ Failed to generate QR code: {}", e);
-            Err(e.into())
+            Err(VaultError::Totp(e))
         },
     }
 }
 
-pub fn get_totp_code(config: &TotpConfig) -> Result<String, Box<dyn std::error::Error>> {
-    let secret_bytes = Secret::Encoded(config.secret.clone()).to_bytes()?;
-    let totp = TOTP::new(
-        config.algorithm,
-        config.digits.into(),
-        config.skew,
-        config.step,
-        secret_bytes,
-        config.issuer.clone(),
-        config.account.clone(),
-    )?;
-    
-    let current_code = totp.generate_current()?;
+pub fn get_totp_code(config: &TotpConfig) -> Result<String, VaultError> {
+    let totp = build_totp(config)?;
+
+    let current_code = totp.generate_current().map_err(|e| VaultError::Totp(e.to_string()))?;
     Ok(current_code.to_string())
 }
 
-pub fn generate_secret() -> String {
+/// 检查 Base32 编码的 TOTP 密钥能否使用（至少 128 位）
+pub fn check_secret(secret: &SecretString) -> Result<(), VaultError> {
+    build_totp(&TotpConfig { secret: secret.clone(), ..TotpConfig::default() })?;
+    Ok(())
+}
+
+pub fn generate_secret() -> SecretString {
     use rand::Rng;
     const CHARSET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
     let mut rng = rand::thread_rng();
     
-    (0..32)
+    let secret: String = (0..32)
         .map(|_| {
             let idx = rng.gen_range(0..CHARSET.len());
             CHARSET[idx] as char
         })
-        .collect()
+        .collect();
+    SecretString::new(secret)
 }
\ No newline at end of file
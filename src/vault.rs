@@ -1,55 +1,730 @@
+use std::fmt;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::Write;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use rand::RngCore;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::config::Config;
+use crate::contents;
+use crate::error::VaultError;
+
+/// 指定保险库目录的环境变量
+pub const VAULT_DIR_ENV: &str = "VAULT_RPG_DIR";
+/// 配置文件中指定保险库目录的键
+pub const VAULT_DIR_KEY: &str = "vault_dir";
+/// 旧版本使用的保险库目录（相对于当前目录）
+pub const LEGACY_DIR: &str = "secrets";
+
+/// 保险库名称的最大字符数
+pub const MAX_NAME_CHARS: usize = 64;
+/// 保险库名称 UTF-8 编码后的最大字节数，加上 `vault_` 前缀和 `.enc` 后缀后仍在文件名长度限制内
+const MAX_NAME_BYTES: usize = 200;
+/// Windows 的设备名不能用作目录名（备份和回收站目录以保险库名称命名），不区分大小写，带扩展名也不行
+const RESERVED_NAMES: [&str; 22] = [
+    "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8", "com9", "lpt1", "lpt2",
+    "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+const FILE_PREFIX: &str = "vault_";
+const FILE_SUFFIX: &str = ".enc";
+const LOCK_EXTENSION: &str = "lock";
+/// 备份目录（位于保险库目录下，每个保险库一个子目录）
+pub const BACKUP_DIR: &str = "backups";
+/// 回收站目录（位于保险库目录下，每个保险库一个子目录）
+pub const TRASH_DIR: &str = "trash";
+/// 配置文件中每个保险库保留的备份数的键，0 表示不备份
+pub const BACKUP_RETENTION_KEY: &str = "backup_retention";
+pub const DEFAULT_BACKUP_RETENTION: usize = 10;
+
+/// 保险库目录和文件的权限（Unix）
+#[cfg(unix)]
+const DIR_MODE: u32 = 0o700;
+#[cfg(unix)]
+const FILE_MODE: u32 = 0o600;
+
+/// 经过校验的保险库名称
+///
+/// 先做 Unicode NFC 规范化，使同一个中文名称的不同输入方式对应同一个文件；之后只允许字母、数字
+/// （含中文等各种文字）和 `-`、`_`、`.`，不能以 `.` 或 `-` 开头，因此不会含有路径分隔符或 `..`。
+/// 也不能以 `.` 结尾或使用 Windows 的设备名，以便用作备份和回收站的目录名。
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VaultName(String);
+
+impl VaultName {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for VaultName {
+    type Err = VaultError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let name: String = s.nfc().collect();
+        let invalid = |reason: &str| VaultError::InvalidInput(format!("Invalid vault name '{}': {}", s, reason));
+        if name.is_empty() {
+            return Err(invalid("it is empty"));
+        }
+        if name.chars().count() > MAX_NAME_CHARS || name.len() > MAX_NAME_BYTES {
+            return Err(invalid(&format!("it is longer than {} characters", MAX_NAME_CHARS)));
+        }
+        if let Some(c) = name.chars().find(|c| !c.is_alphanumeric() && !matches!(c, '-' | '_' | '.')) {
+            return Err(invalid(&format!(
+                "'{}' is not allowed, use letters, digits, '-', '_' and '.'",
+                c.escape_default()
+            )));
+        }
+        if name.starts_with(['.', '-']) {
+            return Err(invalid("it cannot start with '.' or '-'"));
+        }
+        if name.ends_with('.') {
+            return Err(invalid("it cannot end with '.'"));
+        }
+        let stem = name.split('.').next().unwrap_or_default().to_lowercase();
+        if RESERVED_NAMES.contains(&stem.as_str()) {
+            return Err(invalid("it is a reserved device name on Windows"));
+        }
+        Ok(VaultName(name))
+    }
+}
+
+impl fmt::Display for VaultName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Deref for VaultName {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Vault {
-    pub name: String,
-    pub path: String,
+    pub name: VaultName,
+    pub path: PathBuf,
 }
 
+/// 保险库的独占建议锁，释放时自动解锁
+///
+/// 只约束同样加锁的进程：读-改-写操作在读取前加锁，写入完成后才释放，避免并发修改互相覆盖。
+/// 只读操作不加锁，原子替换保证它们总能读到完整的文件。
+#[derive(Debug)]
+pub struct VaultLock {
+    _file: File,
+}
+
+/// 创建目录（新建的目录只有所有者可以访问）
+///
+/// 已存在的目录可能是用户自己的目录（如 `--vault-dir .`），不修改其权限，只在对组或其他用户开放时给出警告。
+fn create_private_dir(dir: &Path) -> Result<(), VaultError> {
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, DIR_MODE);
+    builder.create(dir)?;
+    #[cfg(unix)]
+    if let Some(mode) = loose_dir_mode(dir)? {
+        eprintln!(
+            "⚠️  {} is accessible by other users (mode {:o}), consider `chmod {:o}` on it",
+            dir.display(),
+            mode,
+            DIR_MODE
+        );
+    }
+    Ok(())
+}
+
+/// 创建由本工具管理的备份 / 回收站目录，连同其上一级（`backups`、`trash`）一起收紧为 0700
+///
+/// 这些目录只会由本工具创建，旧版本创建时可能没有限制权限。
+fn create_managed_dir(dir: &Path) -> Result<(), VaultError> {
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, DIR_MODE);
+    builder.create(dir)?;
+    #[cfg(unix)]
+    for managed in [Some(dir), dir.parent()].into_iter().flatten() {
+        use std::os::unix::fs::PermissionsExt;
+        if let Some(mode) = loose_dir_mode(managed)? {
+            std::fs::set_permissions(managed, std::fs::Permissions::from_mode(DIR_MODE))?;
+            eprintln!("🔒 Restricted permissions of {} from {:o} to {:o}", managed.display(), mode, DIR_MODE);
+        }
+    }
+    Ok(())
+}
+
+/// 目录对组或其他用户开放了权限时返回其权限位
+#[cfg(unix)]
+fn loose_dir_mode(dir: &Path) -> Result<Option<u32>, VaultError> {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = std::fs::metadata(dir)?.permissions().mode() & 0o777;
+    Ok((mode & !DIR_MODE != 0).then_some(mode))
+}
+
+/// 判断打开的文件是否仍是 `path` 上的那个文件（没有被删除或替换）
+fn is_current_file(file: &File, path: &Path) -> Result<bool, VaultError> {
+    let on_disk = match std::fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e.into()),
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let opened = file.metadata()?;
+        Ok(opened.dev() == on_disk.dev() && opened.ino() == on_disk.ino())
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (file, on_disk);
+        Ok(true)
+    }
+}
+
+/// 以只有所有者可以读写的权限打开文件
+fn open_private(options: &mut OpenOptions, path: &Path) -> Result<File, VaultError> {
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(options, FILE_MODE);
+    Ok(options.open(path)?)
+}
+
+/// 原子地写入文件：先写入同目录下以 `.` 开头的临时文件并 fsync，再重命名覆盖，最后 fsync 目录
+///
+/// 任何时刻崩溃，磁盘上要么是完整的旧文件，要么是完整的新文件。文件权限为 0600。
+pub fn write_atomic(path: &Path, data: &[u8]) -> Result<(), VaultError> {
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    // 只创建缺失的目录，不改动已有目录的权限（例如导出包所在的目录）
+    if !dir.is_dir() {
+        create_private_dir(dir)?;
+    }
+    let mut suffix = [0u8; 8];
+    rand::thread_rng().fill_bytes(&mut suffix);
+    let suffix: String = suffix.iter().map(|b| format!("{:02x}", b)).collect();
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    // 以 `.` 开头，不会被 list_vaults 当成保险库
+    let tmp_path = dir.join(format!(".{}.{}.tmp", file_name, suffix));
+
+    let result = open_private(OpenOptions::new().write(true).create_new(true), &tmp_path).and_then(|mut file| {
+        file.write_all(data)?;
+        file.sync_all()?;
+        Ok(())
+    });
+    let result = result.and_then(|()| Ok(std::fs::rename(&tmp_path, path)?));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp_path);
+    }
+    result?;
+    sync_dir(dir)
+}
+
+/// 把目录项的变化（新建、重命名）刷到磁盘
+fn sync_dir(dir: &Path) -> Result<(), VaultError> {
+    #[cfg(unix)]
+    File::open(dir)?.sync_all()?;
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
+
+/// 确定保险库目录，优先级：命令行 `--vault-dir` > 环境变量 `VAULT_RPG_DIR` > 配置文件 `vault_dir`
+/// > 用户数据目录（Linux 上为 `$XDG_DATA_HOME/vault_rpg`，默认 `~/.local/share/vault_rpg`）
+pub fn resolve_dir(explicit: Option<&Path>) -> Result<PathBuf, VaultError> {
+    if let Some(dir) = explicit {
+        return Ok(dir.to_path_buf());
+    }
+    if let Some(dir) = std::env::var_os(VAULT_DIR_ENV).filter(|dir| !dir.is_empty()) {
+        return Ok(PathBuf::from(dir));
+    }
+    if let Some(dir) = Config::load()?.get(VAULT_DIR_KEY) {
+        return Ok(PathBuf::from(dir));
+    }
+    dirs::data_dir().map(|dir| dir.join("vault_rpg")).ok_or_else(|| {
+        VaultError::Config(format!(
+            "cannot determine the user data directory, pass --vault-dir or set {}",
+            VAULT_DIR_ENV
+        ))
+    })
+}
 
 impl Vault {
-    pub fn new(name: &str) -> Self {
-        let path = format!("secrets/vault_{}.enc", name);
+    pub fn new(dir: &Path, name: &VaultName) -> Self {
+        let path = dir.join(format!("{}{}{}", FILE_PREFIX, name, FILE_SUFFIX));
         Vault {
-            name: name.to_string(),
+            name: name.clone(),
             path,
         }
     }
 
     pub fn exists(&self) -> bool {
-        std::path::Path::new(&self.path).exists()
+        self.path.exists()
+    }
+
+    fn dir(&self) -> &Path {
+        self.path.parent().unwrap_or(Path::new("."))
+    }
+
+    /// 是否与 `other` 指向同一个保险库文件（目录的写法不同也能识别）
+    pub fn is_same(&self, other: &Vault) -> bool {
+        self.name == other.name
+            && match (self.dir().canonicalize(), other.dir().canonicalize()) {
+                (Ok(dir), Ok(other_dir)) => dir == other_dir,
+                _ => self.path == other.path,
+            }
+    }
+
+    /// 原子地写入保险库，覆盖前先备份原来的文件
+    pub fn save(&self, ciphertext: &[u8]) -> Result<(), VaultError> {
+        self.backup()?;
+        write_atomic(&self.path, ciphertext)
+    }
+
+    /// 加锁但不检查保险库是否存在，用于从备份恢复已删除的保险库
+    pub fn acquire_lock(&self) -> Result<VaultLock, VaultError> {
+        create_private_dir(self.dir())?;
+        let lock_path = self.path.with_extension(LOCK_EXTENSION);
+        loop {
+            let file = open_private(
+                OpenOptions::new().read(true).write(true).create(true).truncate(false),
+                &lock_path,
+            )?;
+            match file.try_lock() {
+                Ok(()) => {}
+                Err(TryLockError::WouldBlock) => {
+                    eprintln!("⏳ Vault '{}' is in use by another process, waiting for it...", self.name);
+                    file.lock()?;
+                }
+                Err(TryLockError::Error(e)) => return Err(e.into()),
+            }
+            // 上一个持锁者可能在释放前删除了锁文件，此时锁住的是已脱离路径的旧文件，需重新打开
+            if is_current_file(&file, &lock_path)? {
+                return Ok(VaultLock { _file: file });
+            }
+        }
     }
 
-    pub fn save(&self, ciphertext: &[u8]) -> std::io::Result<()> {
-        std::fs::create_dir_all("secrets")?;
-        std::fs::write(&self.path, ciphertext)
+    /// 锁定已存在的保险库，用于读-改-写操作
+    pub fn lock(&self) -> Result<VaultLock, VaultError> {
+        if !self.exists() {
+            return Err(VaultError::NotFound(self.name.to_string()));
+        }
+        self.acquire_lock()
     }
 
-    pub fn load(&self) -> std::io::Result<Vec<u8>> {
-        std::fs::read(&self.path)
+    /// 锁定一个将要创建的保险库，加锁后发现已存在时报错
+    pub fn lock_new(&self) -> Result<VaultLock, VaultError> {
+        let lock = self.acquire_lock()?;
+        if self.exists() {
+            return Err(VaultError::AlreadyExists(self.name.to_string()));
+        }
+        Ok(lock)
     }
 
-    /// 列出所有保险柜名称（去除扩展名和前缀）
-    pub fn list_vaults() -> std::io::Result<Vec<String>> {
+    pub fn load(&self) -> Result<Vec<u8>, VaultError> {
+        match std::fs::read(&self.path) {
+            Ok(data) => Ok(data),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(VaultError::NotFound(self.name.to_string())),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// 列出目录中所有保险柜名称（去除扩展名和前缀），按名称排序，目录不存在时为空
+    ///
+    /// 名称不合法或未经规范化的文件不是本程序创建的，直接忽略。
+    pub fn list_vaults(dir: &Path) -> Result<Vec<VaultName>, VaultError> {
         let mut vaults = Vec::new();
-        let dir = std::fs::read_dir("secrets")?;
-        for entry in dir {
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vaults),
+            Err(e) => return Err(e.into()),
+        };
+        for entry in entries {
             let entry = entry?;
             let path = entry.path();
-            if let Some(fname) = path.file_name().and_then(|n| n.to_str()) {
-                if fname.starts_with("vault_") && fname.ends_with(".enc") {
-                    let name = &fname[6..fname.len() - 4];
-                    vaults.push(name.to_string());
-                }
+            if let Some(name) = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .and_then(|fname| fname.strip_prefix(FILE_PREFIX))
+                .and_then(|fname| fname.strip_suffix(FILE_SUFFIX))
+                && let Ok(parsed) = name.parse::<VaultName>()
+                && parsed.as_str() == name
+                && path.is_file()
+            {
+                vaults.push(parsed);
             }
         }
+        vaults.sort();
         Ok(vaults)
     }
 
-    /// 删除该保险柜文件
-    pub fn delete(&self) -> std::io::Result<()> {
-        if self.exists() {
-            std::fs::remove_file(&self.path)
+    /// 把保险库移到回收站（`trash/<名称>/<删除时间>.enc`）并删除锁文件，返回回收站中的文件；调用方应持有锁
+    pub fn trash(&self) -> Result<Snapshot, VaultError> {
+        if !self.exists() {
+            return Err(VaultError::NotFound(self.name.to_string()));
+        }
+        let (id, path) = new_snapshot_path(&self.trash_dir())?;
+        std::fs::rename(&self.path, &path)?;
+        sync_dir(self.dir())?;
+        self.remove_lock_file()?;
+        let created = parse_snapshot_id(&id).unwrap_or_default();
+        Ok(Snapshot { id, created, size: path.metadata()?.len(), path })
+    }
+
+    /// 删除保险库文件而不放入回收站（重命名后删除原文件），调用方应持有锁
+    pub fn remove(&self) -> Result<(), VaultError> {
+        std::fs::remove_file(&self.path)?;
+        sync_dir(self.dir())?;
+        self.remove_lock_file()
+    }
+
+    /// 保险库已不存在时删除遗留的锁文件，调用方应持有锁
+    ///
+    /// 等待中的进程拿到锁后会发现锁文件已不在原路径上并重新加锁，因此不会出现两个进程同时持锁。
+    fn remove_lock_file(&self) -> Result<(), VaultError> {
+        match std::fs::remove_file(self.path.with_extension(LOCK_EXTENSION)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 保险库文件的一份快照：备份（`backups/<名称>/<id>.enc`）或回收站中的文件（`trash/<名称>/<id>.enc`）
+///
+/// id 是备份或删除时的 UTC 时间 `YYYYMMDDTHHMMSSZ`，同一秒内的多份快照追加 `-1`、`-2` 等序号。
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub id: String,
+    pub path: PathBuf,
+    pub created: u64,
+    pub size: u64,
+}
+
+impl Snapshot {
+    /// 按时间先后排序用的键
+    fn sort_key(&self) -> (u64, u32) {
+        let sequence = self.id.split_once('-').and_then(|(_, n)| n.parse().ok()).unwrap_or(0);
+        (self.created, sequence)
+    }
+}
+
+/// 解析 `YYYYMMDDTHHMMSSZ` 形式的快照 id（可带 `-序号`），返回 Unix 秒数
+fn parse_snapshot_id(id: &str) -> Option<u64> {
+    let (stamp, sequence) = match id.split_once('-') {
+        Some((stamp, sequence)) => (stamp, Some(sequence)),
+        None => (id, None),
+    };
+    if sequence.is_some_and(|n| n.is_empty() || !n.bytes().all(|b| b.is_ascii_digit())) {
+        return None;
+    }
+    let digits = stamp.strip_suffix('Z')?;
+    let (date, time) = digits.split_once('T')?;
+    if date.len() != 8 || time.len() != 6 || !date.bytes().chain(time.bytes()).all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let number = |s: &str| s.parse::<i64>().ok();
+    let (year, month, day) = (number(&date[..4])?, number(&date[4..6])?, number(&date[6..])?);
+    let (hour, minute, second) = (number(&time[..2])?, number(&time[2..4])?, number(&time[4..])?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+    // Howard Hinnant 的 days_from_civil 算法
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    u64::try_from(days * 86_400 + hour * 3600 + minute * 60 + second).ok()
+}
+
+/// 把 `2026-01-31 12:00:00`、`2026-01-31T12:00:00Z` 之类的写法规范化为快照 id
+fn normalize_snapshot_id(at: &str) -> String {
+    let at = at.to_uppercase().replace(' ', "T").replace(':', "");
+    match at.split_once('T') {
+        Some((date, time)) => {
+            let time = if time.contains('Z') { time.to_string() } else { format!("{}Z", time) };
+            format!("{}T{}", date.replace('-', ""), time)
+        }
+        None => at,
+    }
+}
+
+fn format_snapshot_id(secs: u64) -> String {
+    let (year, month, day, hour, minute, second) = contents::utc_datetime(secs);
+    format!("{:04}{:02}{:02}T{:02}{:02}{:02}Z", year, month, day, hour, minute, second)
+}
+
+/// 在 `dir` 中为一份新快照分配以当前时间命名的路径
+fn new_snapshot_path(dir: &Path) -> Result<(String, PathBuf), VaultError> {
+    create_managed_dir(dir)?;
+    let stamp = format_snapshot_id(contents::now());
+    let mut id = stamp.clone();
+    let mut sequence = 0;
+    while dir.join(format!("{}{}", id, FILE_SUFFIX)).exists() {
+        sequence += 1;
+        id = format!("{}-{}", stamp, sequence);
+    }
+    let path = dir.join(format!("{}{}", id, FILE_SUFFIX));
+    Ok((id, path))
+}
+
+/// 列出 `dir` 中的快照，按时间从旧到新排序，目录不存在时为空
+fn list_snapshots(dir: &Path) -> Result<Vec<Snapshot>, VaultError> {
+    let mut snapshots = Vec::new();
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(snapshots),
+        Err(e) => return Err(e.into()),
+    };
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        if let Some(id) = path.file_name().and_then(|n| n.to_str()).and_then(|n| n.strip_suffix(FILE_SUFFIX))
+            && let Some(created) = parse_snapshot_id(id)
+            && path.is_file()
+        {
+            snapshots.push(Snapshot { id: id.to_string(), created, size: entry.metadata()?.len(), path });
+        }
+    }
+    snapshots.sort_by_key(Snapshot::sort_key);
+    Ok(snapshots)
+}
+
+/// 按 id 查找快照，`latest` 表示最新的一份
+fn find_snapshot(snapshots: Vec<Snapshot>, at: &str) -> Option<Snapshot> {
+    if at.eq_ignore_ascii_case("latest") {
+        return snapshots.into_iter().next_back();
+    }
+    let normalized = normalize_snapshot_id(at);
+    snapshots.into_iter().find(|snapshot| snapshot.id == at || snapshot.id == normalized)
+}
+
+/// 尽力安全删除：用随机数据覆盖文件内容并 fsync 后再删除
+///
+/// 在 SSD、写时复制文件系统（btrfs、ZFS、APFS）或有快照的系统上，旧数据仍可能残留在别处。
+fn shred_file(path: &Path) -> Result<(), VaultError> {
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    let mut remaining = file.metadata()?.len();
+    let mut buffer = [0u8; 4096];
+    while remaining > 0 {
+        let len = remaining.min(buffer.len() as u64) as usize;
+        rand::thread_rng().fill_bytes(&mut buffer[..len]);
+        file.write_all(&buffer[..len])?;
+        remaining -= len as u64;
+    }
+    file.sync_all()?;
+    drop(file);
+    std::fs::remove_file(path)?;
+    Ok(())
+}
+
+/// 删除快照文件（`shred` 时先覆盖），删空的目录一并删除
+fn remove_snapshots(dir: &Path, snapshots: &[Snapshot], shred: bool) -> Result<(), VaultError> {
+    for snapshot in snapshots {
+        if shred {
+            shred_file(&snapshot.path)?;
         } else {
-            Ok(())
+            std::fs::remove_file(&snapshot.path)?;
+        }
+    }
+    // 目录非空时删除失败，正是想要的结果
+    let _ = std::fs::remove_dir(dir);
+    Ok(())
+}
+
+/// 读取配置的备份保留数
+pub fn backup_retention() -> Result<usize, VaultError> {
+    let config = Config::load()?;
+    match config.get(BACKUP_RETENTION_KEY) {
+        Some(value) => value.parse().map_err(|_| {
+            VaultError::Config(format!(
+                "invalid value for '{}' in {}: {}",
+                BACKUP_RETENTION_KEY,
+                crate::config::config_path().display(),
+                value
+            ))
+        }),
+        None => Ok(DEFAULT_BACKUP_RETENTION),
+    }
+}
+
+impl Vault {
+    fn backup_dir(&self) -> PathBuf {
+        self.dir().join(BACKUP_DIR).join(self.name.as_str())
+    }
+
+    fn trash_dir(&self) -> PathBuf {
+        self.dir().join(TRASH_DIR).join(self.name.as_str())
+    }
+
+    /// 把当前文件复制为一个新备份，并删除超出保留数的旧备份；文件不存在或保留数为 0 时不做任何事
+    fn backup(&self) -> Result<(), VaultError> {
+        let retention = backup_retention()?;
+        if retention == 0 {
+            return Ok(());
         }
+        let data = match std::fs::read(&self.path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        let (_, backup_path) = new_snapshot_path(&self.backup_dir())?;
+        write_atomic(&backup_path, &data)?;
+        self.prune_backups()
     }
-}
\ No newline at end of file
+
+    /// 按 `backup_retention` 删除多余的最旧备份，保留数为 0（不备份）时不做任何删除
+    pub fn prune_backups(&self) -> Result<(), VaultError> {
+        let retention = backup_retention()?;
+        if retention == 0 {
+            return Ok(());
+        }
+        let backups = self.backups()?;
+        remove_snapshots(&self.backup_dir(), &backups[..backups.len().saturating_sub(retention)], false)
+    }
+
+    /// 列出该保险库的所有备份，按时间从旧到新排序；保险库本身已删除时也可以列出
+    pub fn backups(&self) -> Result<Vec<Snapshot>, VaultError> {
+        list_snapshots(&self.backup_dir())
+    }
+
+    /// 按 id 查找备份，`latest` 表示最新的备份
+    pub fn find_backup(&self, at: &str) -> Result<Snapshot, VaultError> {
+        let at = at.trim();
+        find_snapshot(self.backups()?, at).ok_or_else(|| {
+            VaultError::InvalidInput(format!(
+                "Vault '{}' has no backup '{}', run `backups list {}` to see the available ones",
+                self.name, at, self.name
+            ))
+        })
+    }
+
+    /// 以给定的 id 写入一份备份，用于重命名时转移原来的备份
+    pub fn write_backup(&self, id: &str, data: &[u8]) -> Result<(), VaultError> {
+        create_managed_dir(&self.backup_dir())?;
+        write_atomic(&self.backup_dir().join(format!("{}{}", id, FILE_SUFFIX)), data)
+    }
+
+    /// 删除指定的备份，`shred` 时先用随机数据覆盖
+    pub fn remove_backups(&self, backups: &[Snapshot], shred: bool) -> Result<(), VaultError> {
+        remove_snapshots(&self.backup_dir(), backups, shred)
+    }
+
+    /// 该保险库在回收站中的所有文件，按删除时间从旧到新排序
+    pub fn trashed(&self) -> Result<Vec<Snapshot>, VaultError> {
+        list_snapshots(&self.trash_dir())
+    }
+
+    /// 从回收站恢复保险库，`at` 为回收站中的 id 或 `latest`；调用方应通过 `lock_new` 持有锁
+    pub fn restore_from_trash(&self, at: &str) -> Result<Snapshot, VaultError> {
+        let at = at.trim();
+        let snapshot = find_snapshot(self.trashed()?, at).ok_or_else(|| {
+            VaultError::InvalidInput(format!("Vault '{}' has no trashed copy '{}', run `trash list` to see them", self.name, at))
+        })?;
+        std::fs::rename(&snapshot.path, &self.path)?;
+        sync_dir(self.dir())?;
+        remove_snapshots(&self.trash_dir(), &[], false)?;
+        Ok(snapshot)
+    }
+
+    /// 永久删除回收站中的 `snapshots`；保险库已不存在且回收站中也没有剩余的文件时，连同备份一起删除
+    ///
+    /// `shred` 时每个文件先用随机数据覆盖。返回删除的备份，调用方应持有锁。
+    pub fn purge(&self, snapshots: &[Snapshot], shred: bool) -> Result<Vec<Snapshot>, VaultError> {
+        remove_snapshots(&self.trash_dir(), snapshots, shred)?;
+        let mut backups = Vec::new();
+        if !self.exists() && self.trashed()?.is_empty() {
+            backups = self.backups()?;
+            remove_snapshots(&self.backup_dir(), &backups, shred)?;
+            self.remove_lock_file()?;
+        }
+        sync_dir(self.dir())?;
+        Ok(backups)
+    }
+
+    /// 列出回收站中的所有文件，按名称和删除时间排序
+    pub fn list_trash(dir: &Path) -> Result<Vec<(VaultName, Snapshot)>, VaultError> {
+        let mut trashed = Vec::new();
+        let entries = match std::fs::read_dir(dir.join(TRASH_DIR)) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(trashed),
+            Err(e) => return Err(e.into()),
+        };
+        let mut names = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            if let Some(name) = entry.file_name().to_str()
+                && let Ok(parsed) = name.parse::<VaultName>()
+                && parsed.as_str() == name
+                && entry.path().is_dir()
+            {
+                names.push(parsed);
+            }
+        }
+        names.sort();
+        for name in names {
+            for snapshot in Vault::new(dir, &name).trashed()? {
+                trashed.push((name.clone(), snapshot));
+            }
+        }
+        Ok(trashed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(name: &str) -> Result<VaultName, VaultError> {
+        name.parse()
+    }
+
+    #[test]
+    fn accepts_and_normalizes_names() {
+        for name in ["wallet", "my-wallet_2", "cold.storage", "钱包", "Ключ", "a"] {
+            assert_eq!(parse(name).unwrap().as_str(), name);
+        }
+        assert_eq!(parse("  wallet \n").unwrap().as_str(), "wallet");
+        // "é" 的分解形式（e + 组合重音符）与组合形式是同一个名称
+        let decomposed = parse("cafe\u{301}").unwrap();
+        assert_eq!(decomposed, parse("caf\u{e9}").unwrap());
+        assert_eq!(decomposed.as_str(), "caf\u{e9}");
+    }
+
+    #[test]
+    fn rejects_path_traversal() {
+        for name in ["..", ".", "../wallet", "a/b", "a\\b", "/etc/passwd", ".hidden", "-flag", "a\0b", "C:wallet"] {
+            assert!(matches!(parse(name), Err(VaultError::InvalidInput(_))), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn rejects_reserved_names() {
+        for name in ["con", "NUL", "Aux.wallet", "com1", "lpt9.txt", "wallet."] {
+            assert!(matches!(parse(name), Err(VaultError::InvalidInput(_))), "{:?}", name);
+        }
+        for name in ["console", "nullable", "com10", "wallet.con"] {
+            assert!(parse(name).is_ok(), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn enforces_length_limits() {
+        assert!(parse("").is_err());
+        assert!(parse("   ").is_err());
+        assert!(parse(&"a".repeat(MAX_NAME_CHARS)).is_ok());
+        assert!(parse(&"a".repeat(MAX_NAME_CHARS + 1)).is_err());
+        // 按字符数计算，中文名称不会因为 UTF-8 字节数而提前超限
+        assert!(parse(&"钱".repeat(MAX_NAME_CHARS)).is_ok());
+        assert!(parse(&"钱".repeat(MAX_NAME_CHARS + 1)).is_err());
+    }
+}
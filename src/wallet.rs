@@ -0,0 +1,316 @@
+// vault_rpg/src/wallet.rs
+
+use blake2::Blake2b;
+use blake2::digest::consts::U32;
+use hmac::{Hmac, Mac};
+use k256::elliptic_curve::PrimeField;
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+use k256::{NonZeroScalar, Scalar, SecretKey};
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256, Sha512};
+use sha3::Keccak256;
+use zeroize::Zeroizing;
+
+use crate::error::VaultError;
+use crate::mnemonic;
+use crate::secret::SecretString;
+
+const HARDENED: u32 = 0x8000_0000;
+/// 主网 xpub 的版本字节
+const XPUB_VERSION: [u8; 4] = [0x04, 0x88, 0xb2, 0x1e];
+
+/// 支持派生地址的链
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chain {
+    /// BIP-84 原生隔离见证地址，`m/84'/0'/account'/0/i`
+    Bitcoin,
+    /// BIP-44，`m/44'/60'/account'/0/i`
+    Ethereum,
+    /// SLIP-10 ed25519，`m/44'/784'/account'/0'/i'`
+    Sui,
+}
+
+impl Chain {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Chain::Bitcoin => "btc",
+            Chain::Ethereum => "eth",
+            Chain::Sui => "sui",
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Self, VaultError> {
+        match name.to_lowercase().as_str() {
+            "btc" | "bitcoin" => Ok(Chain::Bitcoin),
+            "eth" | "ethereum" => Ok(Chain::Ethereum),
+            "sui" => Ok(Chain::Sui),
+            _ => Err(VaultError::InvalidInput(format!(
+                "Unsupported chain '{}', expected btc, eth or sui",
+                name
+            ))),
+        }
+    }
+
+    /// 第 `index` 个收款地址的派生路径
+    pub fn path(&self, account: u32, index: u32) -> String {
+        match self {
+            Chain::Bitcoin => format!("m/84'/0'/{}'/0/{}", account, index),
+            Chain::Ethereum => format!("m/44'/60'/{}'/0/{}", account, index),
+            Chain::Sui => format!("m/44'/784'/{}'/0'/{}'", account, index),
+        }
+    }
+}
+
+/// 由助记词和 BIP-39 密码生成 64 字节种子
+pub fn seed(mnemonic: &SecretString, passphrase: &SecretString) -> Result<Zeroizing<[u8; 64]>, VaultError> {
+    let mnemonic = mnemonic::parse(mnemonic)?;
+    Ok(Zeroizing::new(mnemonic.to_seed(passphrase.expose())))
+}
+
+fn hmac_sha512(key: &[u8], parts: &[&[u8]]) -> Zeroizing<[u8; 64]> {
+    let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    for part in parts {
+        mac.update(part);
+    }
+    Zeroizing::new(mac.finalize().into_bytes().into())
+}
+
+fn hash160(data: &[u8]) -> [u8; 20] {
+    Ripemd160::digest(Sha256::digest(data)).into()
+}
+
+/// 解析 `m/84'/0'/0'/0/0` 形式的路径，`'` 或 `h` 表示硬化
+fn parse_path(path: &str) -> Result<Vec<u32>, VaultError> {
+    let invalid = || VaultError::InvalidInput(format!("Invalid derivation path '{}'", path));
+    let mut parts = path.split('/');
+    if parts.next() != Some("m") {
+        return Err(invalid());
+    }
+    parts
+        .map(|part| {
+            let (number, hardened) = match part.strip_suffix(['\'', 'h']) {
+                Some(number) => (number, true),
+                None => (part, false),
+            };
+            let index: u32 = number.parse().map_err(|_| invalid())?;
+            if index >= HARDENED {
+                return Err(invalid());
+            }
+            Ok(if hardened { index | HARDENED } else { index })
+        })
+        .collect()
+}
+
+/// BIP-32 secp256k1 扩展私钥
+pub struct ExtendedKey {
+    secret: SecretKey,
+    chain_code: Zeroizing<[u8; 32]>,
+    depth: u8,
+    parent_fingerprint: [u8; 4],
+    child_number: u32,
+}
+
+impl ExtendedKey {
+    pub fn master(seed: &[u8]) -> Result<Self, VaultError> {
+        let i = hmac_sha512(b"Bitcoin seed", &[seed]);
+        let secret = SecretKey::from_slice(&i[..32])
+            .map_err(|_| VaultError::Wallet("seed produced an invalid master key".into()))?;
+        let mut chain_code = Zeroizing::new([0u8; 32]);
+        chain_code.copy_from_slice(&i[32..]);
+        Ok(ExtendedKey { secret, chain_code, depth: 0, parent_fingerprint: [0; 4], child_number: 0 })
+    }
+
+    pub fn derive_path(&self, path: &str) -> Result<Self, VaultError> {
+        let mut key = ExtendedKey {
+            secret: self.secret.clone(),
+            chain_code: self.chain_code.clone(),
+            depth: self.depth,
+            parent_fingerprint: self.parent_fingerprint,
+            child_number: self.child_number,
+        };
+        for index in parse_path(path)? {
+            key = key.derive_child(index)?;
+        }
+        Ok(key)
+    }
+
+    fn derive_child(&self, index: u32) -> Result<Self, VaultError> {
+        let i = if index >= HARDENED {
+            hmac_sha512(&*self.chain_code, &[&[0], &self.secret.to_bytes(), &index.to_be_bytes()])
+        } else {
+            hmac_sha512(&*self.chain_code, &[&self.public_key(), &index.to_be_bytes()])
+        };
+        let tweak: Option<Scalar> = Scalar::from_repr(*k256::FieldBytes::from_slice(&i[..32])).into();
+        let invalid = || VaultError::Wallet(format!("child key {} is invalid, use the next index", index));
+        let child = tweak.ok_or_else(invalid)? + *self.secret.to_nonzero_scalar();
+        let child: Option<NonZeroScalar> = NonZeroScalar::new(child).into();
+        let mut chain_code = Zeroizing::new([0u8; 32]);
+        chain_code.copy_from_slice(&i[32..]);
+        Ok(ExtendedKey {
+            secret: SecretKey::from(child.ok_or_else(invalid)?),
+            chain_code,
+            depth: self.depth.checked_add(1).ok_or_else(invalid)?,
+            parent_fingerprint: self.fingerprint(),
+            child_number: index,
+        })
+    }
+
+    /// 压缩格式公钥（33 字节）
+    pub fn public_key(&self) -> [u8; 33] {
+        let point = self.secret.public_key().to_encoded_point(true);
+        point.as_bytes().try_into().expect("compressed point is 33 bytes")
+    }
+
+    /// 公钥 HASH160 的前 4 字节
+    pub fn fingerprint(&self) -> [u8; 4] {
+        let mut fingerprint = [0u8; 4];
+        fingerprint.copy_from_slice(&hash160(&self.public_key())[..4]);
+        fingerprint
+    }
+
+    /// Base58Check 编码的扩展公钥
+    pub fn xpub(&self) -> String {
+        let mut data = Vec::with_capacity(78);
+        data.extend_from_slice(&XPUB_VERSION);
+        data.push(self.depth);
+        data.extend_from_slice(&self.parent_fingerprint);
+        data.extend_from_slice(&self.child_number.to_be_bytes());
+        data.extend_from_slice(&*self.chain_code);
+        data.extend_from_slice(&self.public_key());
+        bs58::encode(data).with_check().into_string()
+    }
+}
+
+/// SLIP-10 ed25519 派生，只支持硬化路径，返回公钥
+fn ed25519_public_key(seed: &[u8], path: &str) -> Result<[u8; 32], VaultError> {
+    let mut i = hmac_sha512(b"ed25519 seed", &[seed]);
+    for index in parse_path(path)? {
+        if index < HARDENED {
+            return Err(VaultError::InvalidInput(format!("ed25519 path '{}' must be fully hardened", path)));
+        }
+        i = hmac_sha512(&i[32..], &[&[0], &i[..32], &index.to_be_bytes()]);
+    }
+    let mut secret = Zeroizing::new([0u8; 32]);
+    secret.copy_from_slice(&i[..32]);
+    Ok(ed25519_dalek::SigningKey::from_bytes(&secret).verifying_key().to_bytes())
+}
+
+fn bitcoin_address(key: &ExtendedKey) -> Result<String, VaultError> {
+    bech32::segwit::encode_v0(bech32::hrp::BC, &hash160(&key.public_key()))
+        .map_err(|e| VaultError::Wallet(e.to_string()))
+}
+
+/// 带 EIP-55 大小写校验的以太坊地址
+fn ethereum_address(key: &ExtendedKey) -> String {
+    let point = key.secret.public_key().to_encoded_point(false);
+    let hash = Keccak256::digest(&point.as_bytes()[1..]);
+    let address: String = hash[12..].iter().map(|b| format!("{:02x}", b)).collect();
+    let checksum = Keccak256::digest(address.as_bytes());
+    let address: String = address
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = (checksum[i / 2] >> if i % 2 == 0 { 4 } else { 0 }) & 0x0f;
+            if nibble >= 8 { c.to_ascii_uppercase() } else { c }
+        })
+        .collect();
+    format!("0x{}", address)
+}
+
+/// Sui 地址：BLAKE2b-256(0x00 || ed25519 公钥)
+fn sui_address(public_key: &[u8; 32]) -> String {
+    let mut hasher = Blake2b::<U32>::new();
+    hasher.update([0x00]);
+    hasher.update(public_key);
+    let address: String = hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect();
+    format!("0x{}", address)
+}
+
+/// 派生 `chain` 的前 `count` 个收款地址，返回 (路径, 地址)
+pub fn addresses(seed: &[u8], chain: Chain, account: u32, count: u32) -> Result<Vec<(String, String)>, VaultError> {
+    let master = ExtendedKey::master(seed)?;
+    (0..count)
+        .map(|index| {
+            let path = chain.path(account, index);
+            let address = match chain {
+                Chain::Bitcoin => bitcoin_address(&master.derive_path(&path)?)?,
+                Chain::Ethereum => ethereum_address(&master.derive_path(&path)?),
+                Chain::Sui => sui_address(&ed25519_public_key(seed, &path)?),
+            };
+            Ok((path, address))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MNEMONIC: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    fn unhex(text: &str) -> Vec<u8> {
+        (0..text.len()).step_by(2).map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap()).collect()
+    }
+
+    fn abandon_seed() -> Zeroizing<[u8; 64]> {
+        seed(&SecretString::from(MNEMONIC), &SecretString::from("")).unwrap()
+    }
+
+    #[test]
+    fn bip39_seed_vector() {
+        let seed = seed(&SecretString::from(MNEMONIC), &SecretString::from("TREZOR")).unwrap();
+        assert_eq!(
+            seed.as_slice(),
+            unhex(
+                "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04"
+            )
+        );
+    }
+
+    #[test]
+    fn bip32_vector() {
+        // BIP-32 测试向量 1
+        let master = ExtendedKey::master(&unhex("000102030405060708090a0b0c0d0e0f")).unwrap();
+        assert_eq!(
+            master.xpub(),
+            "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8"
+        );
+        assert_eq!(
+            master.derive_path("m/0'").unwrap().xpub(),
+            "xpub68Gmy5EdvgibQVfPdqkBBCHxA5htiqg55crXYuXoQRKfDBFA1WEjWgP6LHhwBZeNK1VTsfTFUHCdrfp1bgwQ9xv5ski8PX9rL2dZXvgGDnw"
+        );
+    }
+
+    #[test]
+    fn slip10_ed25519_vector() {
+        // SLIP-10 ed25519 测试向量 1
+        let seed = unhex("000102030405060708090a0b0c0d0e0f");
+        assert_eq!(
+            ed25519_public_key(&seed, "m/0'").unwrap().as_slice(),
+            unhex("8c8a13df77a28f3445213a0f432fde644acaa215fc72dcdf300d5efaa85d350c")
+        );
+        assert!(ed25519_public_key(&seed, "m/0").is_err());
+    }
+
+    #[test]
+    fn abandon_about_vectors() {
+        let seed = abandon_seed();
+        assert_eq!(ExtendedKey::master(seed.as_slice()).unwrap().fingerprint(), [0x73, 0xc5, 0xda, 0x0a]);
+
+        let bitcoin = addresses(seed.as_slice(), Chain::Bitcoin, 0, 2).unwrap();
+        assert_eq!(bitcoin[0], ("m/84'/0'/0'/0/0".to_string(), "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu".to_string()));
+        assert_eq!(bitcoin[1].1, "bc1qnjg0jd8228aq7egyzacy8cys3knf9xvrerkf9g");
+
+        let ethereum = addresses(seed.as_slice(), Chain::Ethereum, 0, 1).unwrap();
+        assert_eq!(ethereum[0], ("m/44'/60'/0'/0/0".to_string(), "0x9858EfFD232B4033E47d90003D41EC34EcaEda94".to_string()));
+
+        let sui = addresses(seed.as_slice(), Chain::Sui, 0, 1).unwrap();
+        assert_eq!(
+            sui[0],
+            (
+                "m/44'/784'/0'/0'/0'".to_string(),
+                "0x5e93a736d04fbb25737aa40bee40171ef79f65fae833749e3c089fe7cc2161f1".to_string()
+            )
+        );
+    }
+}